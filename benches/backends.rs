@@ -0,0 +1,65 @@
+//! Times `count_solutions_with_mode` for every backend. Run with `cargo bench`. The backends are
+//! timed searching exhaustively, apart from the `mirror` column, which is the `bits64` backend
+//! with mirror pruning. The last column is how many times faster the exhaustive `bits64` backend
+//! is than the `board` backend.
+
+use nqueens::{Backend, SearchMode};
+use std::time::{Duration, Instant};
+
+const SIZES: std::ops::RangeInclusive<usize> = 8..=16;
+const RUNS: usize = 3;
+
+/// Each backend with the largest size it's timed at. The board backend and the heap-allocated
+/// masks of the wide backend take minutes per run past these.
+const BACKENDS: [(&str, Backend, SearchMode, usize); 5] = [
+    ("board", Backend::Board, SearchMode::Exhaustive, 12),
+    ("bits64", Backend::Bits64, SearchMode::Exhaustive, 16),
+    ("bits128", Backend::Bits128, SearchMode::Exhaustive, 16),
+    ("wide", Backend::BitsWide, SearchMode::Exhaustive, 14),
+    ("mirror", Backend::Bits64, SearchMode::Mirror, 16),
+];
+
+fn main() {
+    print!("{:>4} {:>10}", "n", "solutions");
+    for (name, _, _, _) in &BACKENDS {
+        print!(" {:>12}", name);
+    }
+    println!(" {:>9}", "speedup");
+
+    for side_size in SIZES {
+        let mut times = vec![];
+        let mut solutions = 0;
+        for &(_, backend, mode, max_side_size) in &BACKENDS {
+            if side_size > max_side_size {
+                times.push(None);
+                continue;
+            }
+            let (count, time) = best_of(RUNS, || nqueens::count_solutions_with_mode(side_size, backend, mode));
+            solutions = count;
+            times.push(Some(time));
+        }
+
+        print!("{:>4} {:>10}", side_size, solutions);
+        for time in &times {
+            match time {
+                Some(time) => print!(" {:>12}", format!("{:.2?}", time)),
+                None => print!(" {:>12}", "-"),
+            }
+        }
+        match (times[0], times[1]) {
+            (Some(board), Some(bits)) => println!(" {:>8.1}x", board.as_secs_f64() / bits.as_secs_f64()),
+            _ => println!(" {:>9}", "-"),
+        }
+    }
+}
+
+fn best_of(runs: usize, f: impl Fn() -> usize) -> (usize, Duration) {
+    (0..runs)
+        .map(|_| {
+            let start = Instant::now();
+            let result = f();
+            (result, start.elapsed())
+        })
+        .min_by_key(|&(_, time)| time)
+        .unwrap()
+}
@@ -0,0 +1,351 @@
+//! A compact binary file format for the solutions of one board size, for when there are too many
+//! of them to keep as text. A file starts with a 20-byte header, all numbers little-endian:
+//!
+//! | bytes   | contents                                                      |
+//! |---------|---------------------------------------------------------------|
+//! | 0..4    | the magic number `NQB1`                                       |
+//! | 4..8    | the side size `n` as a `u32`                                  |
+//! | 8..12   | flags as a `u32`: bit 0 is set for toroidal boards, the rest are 0 |
+//! | 12..20  | the number of solutions as a `u64`                            |
+//!
+//! The solutions follow one after another. Each is the row of every column's queen from the
+//! leftmost column rightwards, in as few bits as hold `n - 1`, packed from the lowest bit of each
+//! byte up and padded to a whole byte. Every solution takes the same number of bytes, so the
+//! index of the file is implicit: solution `k` starts at byte `20 + k * record_len`, and
+//! [`BinaryReader::get`] seeks straight to it.
+//!
+//! The 14,772,512 solutions for `n = 16` take 8 bytes each, about 118 MB in all, a quarter of what
+//! the permutation notation takes.
+
+use crate::board::{Board, Geometry};
+use std::convert::{TryFrom, TryInto};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+const MAGIC: &[u8; 4] = b"NQB1";
+const HEADER_LEN: u64 = 20;
+const TOROIDAL: u32 = 1;
+/// The biggest board a file can be for. Each solution on it takes 128 KiB.
+const MAX_SIDE_SIZE: usize = 1 << 16;
+
+/// The number of bits each row takes on a board `side_size` rows tall.
+fn row_bits(side_size: usize) -> usize {
+    (usize::BITS - side_size.saturating_sub(1).leading_zeros()) as usize
+}
+
+/// The number of bytes each solution takes on a `side_size`×`side_size` board.
+fn record_len(side_size: usize) -> usize {
+    (side_size * row_bits(side_size)).div_ceil(8)
+}
+
+fn invalid_data(message: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.into())
+}
+
+impl Board {
+    /// The board packed the way the binary format stores a solution, or `None` unless it's square
+    /// with exactly one queen in every column.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// // Two bits per row: 1, 3, 0 and 2.
+    /// assert_eq!(Board::from_rows(&[1, 3, 0, 2]).unwrap().to_packed_rows(), Some(vec![0b10_00_11_01]));
+    /// ```
+    pub fn to_packed_rows(&self) -> Option<Vec<u8>> {
+        if !self.is_square() {
+            return None;
+        }
+        let side_size = self.side_size();
+        let bits = row_bits(side_size);
+        let mut bytes = vec![0; record_len(side_size)];
+        for (x, row) in self.rows()?.into_iter().enumerate() {
+            for bit in (0..bits).filter(|&bit| row >> bit & 1 == 1) {
+                let i = x * bits + bit;
+                bytes[i / 8] |= 1 << (i % 8);
+            }
+        }
+        Some(bytes)
+    }
+
+    /// Unpacks a solution stored the way [`to_packed_rows`](Board::to_packed_rows) packs it, or
+    /// returns `None` if `bytes` is the wrong length or a row lies off the board.
+    pub fn from_packed_rows(side_size: usize, bytes: &[u8]) -> Option<Board> {
+        if bytes.len() != record_len(side_size) {
+            return None;
+        }
+        let bits = row_bits(side_size);
+        let rows: Vec<_> = (0..side_size)
+            .map(|x| (0..bits).filter(|bit| {
+                let i = x * bits + bit;
+                bytes[i / 8] >> (i % 8) & 1 == 1
+            }).map(|bit| 1 << bit).sum())
+            .collect();
+        Board::from_rows(&rows)
+    }
+}
+
+/// Writes solutions of one board size to a binary file. The header's count is filled in by
+/// [`finish`](BinaryWriter::finish), which is why the output has to be seekable.
+///
+/// ```
+/// use nqueens::{BinaryReader, BinaryWriter, Board, Geometry};
+/// use std::io::Cursor;
+///
+/// let mut writer = BinaryWriter::new(Cursor::new(vec![]), 8, Geometry::Flat).unwrap();
+/// for board in Board::solutions(8) {
+///     writer.write(&board).unwrap();
+/// }
+/// let file = writer.finish().unwrap();
+/// assert_eq!(file.get_ref().len(), 20 + 92 * 3);
+///
+/// let mut reader = BinaryReader::new(file).unwrap();
+/// assert_eq!(reader.boards(), 92);
+/// assert_eq!(reader.get(91).unwrap(), Board::solutions(8).last().unwrap());
+/// ```
+#[derive(Debug)]
+pub struct BinaryWriter<W: Write + Seek> {
+    out: W,
+    side_size: usize,
+    geometry: Geometry,
+    count: u64,
+}
+
+impl<W: Write + Seek> BinaryWriter<W> {
+    /// Starts a file of solutions for `side_size`×`side_size` boards with the given geometry,
+    /// writing the header at the current position of `out`. Fails with
+    /// [`io::ErrorKind::InvalidInput`] for boards more than 65,536 squares across.
+    pub fn new(mut out: W, side_size: usize, geometry: Geometry) -> io::Result<BinaryWriter<W>> {
+        let start = out.stream_position()?;
+        if start != 0 {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "binary files must start at the beginning of the output"));
+        }
+        if side_size > MAX_SIDE_SIZE {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "board too big"));
+        }
+        let side = u32::try_from(side_size).expect("side size checked above");
+        let flags = if geometry == Geometry::Toroidal { TOROIDAL } else { 0 };
+        out.write_all(MAGIC)?;
+        out.write_all(&side.to_le_bytes())?;
+        out.write_all(&flags.to_le_bytes())?;
+        out.write_all(&0u64.to_le_bytes())?;
+        Ok(BinaryWriter { out, side_size, geometry, count: 0 })
+    }
+
+    /// Writes the next solution. Fails with [`io::ErrorKind::InvalidInput`] if the board isn't
+    /// the file's size and geometry, doesn't hold exactly one queen per column or has pawns or
+    /// blocked squares, which the format has no room for, in which case nothing is written. The
+    /// board doesn't have to be valid otherwise.
+    pub fn write(&mut self, board: &Board) -> io::Result<()> {
+        if !board.blocked_squares().is_empty() || !board.pawns().is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "binary files can't hold boards with pawns or blocked squares",
+            ));
+        }
+        let fits = board.width() == self.side_size && board.height() == self.side_size && board.geometry() == self.geometry;
+        let bytes = board.to_packed_rows().filter(|_| fits).ok_or_else(|| io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("binary files for size {} hold one queen in every column of a {0}x{0} board", self.side_size),
+        ))?;
+        self.out.write_all(&bytes)?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// The number of solutions written so far.
+    pub fn boards(&self) -> u64 {
+        self.count
+    }
+
+    /// Fills in the header's count, flushes the writer and hands back what it was writing to,
+    /// positioned at the end of the file.
+    pub fn finish(mut self) -> io::Result<W> {
+        let end = self.out.stream_position()?;
+        self.out.seek(SeekFrom::Start(12))?;
+        self.out.write_all(&self.count.to_le_bytes())?;
+        self.out.seek(SeekFrom::Start(end))?;
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// Reads solutions back from a binary file, in order as an iterator or one at a time by index.
+#[derive(Debug)]
+pub struct BinaryReader<R: Read + Seek> {
+    input: R,
+    side_size: usize,
+    geometry: Geometry,
+    count: u64,
+    /// The index of the solution the iterator yields next.
+    next: u64,
+}
+
+impl<R: Read + Seek> BinaryReader<R> {
+    /// Reads the header from the start of `input`. Fails with [`io::ErrorKind::InvalidData`] if
+    /// it isn't a binary solution file, or is too short to hold the solutions the header says it
+    /// does.
+    pub fn new(mut input: R) -> io::Result<BinaryReader<R>> {
+        let mut header = [0; HEADER_LEN as usize];
+        input.seek(SeekFrom::Start(0))?;
+        input.read_exact(&mut header).map_err(|e| match e.kind() {
+            io::ErrorKind::UnexpectedEof => invalid_data("file too short for a binary solution header"),
+            _ => e,
+        })?;
+        if &header[0..4] != MAGIC {
+            return Err(invalid_data("not a binary solution file"));
+        }
+        let side_size = u32::from_le_bytes(header[4..8].try_into().unwrap()) as usize;
+        let flags = u32::from_le_bytes(header[8..12].try_into().unwrap());
+        let count = u64::from_le_bytes(header[12..20].try_into().unwrap());
+        if flags & !TOROIDAL != 0 {
+            return Err(invalid_data(format!("unknown flags {:#x}", flags)));
+        }
+        if side_size > MAX_SIDE_SIZE {
+            return Err(invalid_data(format!("side size {} is too big", side_size)));
+        }
+        let end = input.seek(SeekFrom::End(0))?;
+        let len = count.checked_mul(record_len(side_size) as u64).and_then(|len| len.checked_add(HEADER_LEN));
+        if len.is_none_or(|len| len > end) {
+            return Err(invalid_data(format!("file too short for {} solutions", count)));
+        }
+        let geometry = if flags & TOROIDAL != 0 { Geometry::Toroidal } else { Geometry::Flat };
+        Ok(BinaryReader { input, side_size, geometry, count, next: 0 })
+    }
+
+    pub fn side_size(&self) -> usize {
+        self.side_size
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// The number of solutions in the file.
+    pub fn boards(&self) -> u64 {
+        self.count
+    }
+
+    /// Solution `k`, counting from 0. Fails with [`io::ErrorKind::InvalidInput`] if there are
+    /// only `k` solutions or fewer, and with [`io::ErrorKind::InvalidData`] if the solution is
+    /// corrupt. The iterator carries on from the solution after it.
+    pub fn get(&mut self, k: u64) -> io::Result<Board> {
+        if k >= self.count {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("solution {} asked for, but there are only {}", k, self.count),
+            ));
+        }
+        let len = record_len(self.side_size);
+        let start = k.checked_mul(len as u64).and_then(|start| start.checked_add(HEADER_LEN))
+            .ok_or_else(|| invalid_data(format!("solution {} lies past the end of any file", k)))?;
+        self.input.seek(SeekFrom::Start(start))?;
+        let mut bytes = vec![0; len];
+        self.input.read_exact(&mut bytes)?;
+        self.next = k + 1;
+        let board = Board::from_packed_rows(self.side_size, &bytes)
+            .ok_or_else(|| invalid_data(format!("solution {} has a row off the board", k)))?;
+        Ok(board.with_geometry(self.geometry).expect("binary file boards are square"))
+    }
+}
+
+impl<R: Read + Seek> Iterator for BinaryReader<R> {
+    type Item = io::Result<Board>;
+
+    fn next(&mut self) -> Option<io::Result<Board>> {
+        if self.next >= self.count {
+            return None;
+        }
+        Some(self.get(self.next))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Square;
+    use std::io::Cursor;
+
+    fn write_file(side_size: usize, geometry: Geometry, boards: &[Board]) -> Vec<u8> {
+        let mut writer = BinaryWriter::new(Cursor::new(vec![]), side_size, geometry).unwrap();
+        for board in boards {
+            writer.write(board).unwrap();
+        }
+        assert_eq!(writer.boards(), boards.len() as u64);
+        writer.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for side_size in 0..=11 {
+            let boards: Vec<_> = Board::solutions(side_size).collect();
+            let file = write_file(side_size, Geometry::Flat, &boards);
+            assert_eq!(file.len(), 20 + boards.len() * record_len(side_size), "side size {}", side_size);
+
+            let reader = BinaryReader::new(Cursor::new(&file)).unwrap();
+            assert_eq!((reader.side_size(), reader.boards()), (side_size, boards.len() as u64));
+            let read: Vec<_> = reader.map(Result::unwrap).collect();
+            assert_eq!(read, boards, "side size {}", side_size);
+        }
+        assert_eq!((row_bits(8), row_bits(9), row_bits(1), row_bits(0)), (3, 4, 0, 0));
+    }
+
+    #[test]
+    fn test_random_access() {
+        let boards: Vec<_> = Board::toroidal_solutions(7).collect();
+        let file = write_file(7, Geometry::Toroidal, &boards);
+        let mut reader = BinaryReader::new(Cursor::new(file)).unwrap();
+        assert_eq!(reader.geometry(), Geometry::Toroidal);
+        for k in [27, 0, 13, 5] {
+            assert_eq!(reader.get(k).unwrap(), boards[k as usize]);
+        }
+        assert_eq!(reader.next().unwrap().unwrap(), boards[6]);
+        assert_eq!(reader.boards(), 28);
+        assert_eq!(reader.get(28).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+
+    #[test]
+    fn test_bad_input() {
+        let mut writer = BinaryWriter::new(Cursor::new(vec![]), 4, Geometry::Flat).unwrap();
+        let solution = Board::from_rows(&[1, 3, 0, 2]).unwrap();
+        let bad_boards = [
+            Board::new(4),
+            Board::from_rows(&[0, 1, 2]).unwrap(),
+            solution.clone().with_geometry(Geometry::Toroidal).unwrap(),
+            solution.clone().with_blocked_squares(vec![Square::new(0, 0)]).unwrap(),
+            solution.with_pawns(vec![Square::new(1, 1)]).unwrap(),
+        ];
+        for board in bad_boards {
+            assert_eq!(writer.write(&board).unwrap_err().kind(), io::ErrorKind::InvalidInput);
+        }
+        assert_eq!(writer.finish().unwrap().into_inner().len(), 20);
+
+        let file = write_file(5, Geometry::Flat, &Board::solutions(5).collect::<Vec<_>>());
+        let mut corrupt = file.clone();
+        corrupt[0] = b'X';
+        assert_eq!(BinaryReader::new(Cursor::new(corrupt)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        let mut corrupt = file.clone();
+        corrupt[8] = 2;
+        assert_eq!(BinaryReader::new(Cursor::new(corrupt)).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(BinaryReader::new(Cursor::new(&file[..10])).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(BinaryReader::new(Cursor::new(&file[..file.len() - 1])).unwrap_err().kind(), io::ErrorKind::InvalidData);
+
+        // Headers promising more than the file holds are caught before anything is allocated.
+        let header = |side: u32, count: u64| [&MAGIC[..], &side.to_le_bytes(), &[0; 4], &count.to_le_bytes()].concat();
+        for (side, count) in [(u32::MAX, 1), (1 << 16 | 1, 0), (8, 1), (8, u64::MAX)] {
+            let error = BinaryReader::new(Cursor::new(header(side, count))).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "side {}, count {}", side, count);
+        }
+        assert_eq!(BinaryReader::new(Cursor::new(header(1 << 16, 0))).unwrap().boards(), 0);
+        assert_eq!(
+            BinaryWriter::new(Cursor::new(vec![]), (1 << 16) + 1, Geometry::Flat).unwrap_err().kind(),
+            io::ErrorKind::InvalidInput,
+        );
+
+        // Three bits per row leave room for rows 5 to 7, which aren't on the board.
+        let mut corrupt = file;
+        corrupt[20] = 0xff;
+        let mut reader = BinaryReader::new(Cursor::new(corrupt)).unwrap();
+        assert_eq!(reader.get(0).unwrap_err().kind(), io::ErrorKind::InvalidData);
+        assert_eq!(Board::from_packed_rows(5, &[0; 3]), None);
+    }
+}
@@ -0,0 +1,772 @@
+//! Backtracking over bitmasks. Instead of re-checking the whole board after every insertion, the
+//! search carries three row sets down the recursion: the rows already holding a queen, and the
+//! rows attacked in the current column along each diagonal direction. Moving to the next column
+//! just shifts the diagonal sets by one row, or rotates them on toroidal boards.
+
+use crate::board::{Board, Geometry, Queen, Square};
+use crate::search::SearchMode;
+use rayon::prelude::*;
+
+/// A set of rows on a board, stored as a bitmask.
+pub(crate) trait RowSet: Clone + Send + Sync {
+    /// The largest board the set can describe, or `None` if it's only limited by memory.
+    const MAX_SIDE_SIZE: Option<usize>;
+
+    fn empty(side_size: usize) -> Self;
+    /// The set holding every row of a `side_size`-row board.
+    fn full(side_size: usize) -> Self;
+    fn insert(&mut self, row: usize);
+    fn contains(&self, row: usize) -> bool;
+    fn union(&self, other: &Self) -> Self;
+    fn difference(&self, other: &Self) -> Self;
+    /// Moves every row one step down the board, dropping the rows that fall off `full`.
+    fn shift_down(&self, full: &Self) -> Self;
+    /// Moves every row one step up the board, dropping row 0.
+    fn shift_up(&self) -> Self;
+    /// Removes and returns the lowest-numbered row in the set.
+    fn pop_first(&mut self) -> Option<usize>;
+    /// The number of rows in the set.
+    fn len(&self) -> usize;
+
+    /// Moves every row one step down a `height`-row board, wrapping the bottom row around to
+    /// the top.
+    fn rotate_down(&self, full: &Self, height: usize) -> Self {
+        let mut rotated = self.shift_down(full);
+        if height > 0 && self.contains(height - 1) {
+            rotated.insert(0);
+        }
+        rotated
+    }
+
+    /// Moves every row one step up a `height`-row board, wrapping the top row around to the
+    /// bottom.
+    fn rotate_up(&self, height: usize) -> Self {
+        let mut rotated = self.shift_up();
+        if height > 0 && self.contains(0) {
+            rotated.insert(height - 1);
+        }
+        rotated
+    }
+}
+
+macro_rules! impl_row_set_for_int {
+    ($int:ty) => {
+        impl RowSet for $int {
+            const MAX_SIDE_SIZE: Option<usize> = Some(<$int>::BITS as usize);
+
+            fn empty(_side_size: usize) -> Self {
+                0
+            }
+
+            fn full(side_size: usize) -> Self {
+                assert!(side_size <= <$int>::BITS as usize);
+                if side_size == <$int>::BITS as usize {
+                    !0
+                } else {
+                    (1 << side_size) - 1
+                }
+            }
+
+            fn insert(&mut self, row: usize) {
+                *self |= 1 << row;
+            }
+
+            fn contains(&self, row: usize) -> bool {
+                self >> row & 1 == 1
+            }
+
+            fn union(&self, other: &Self) -> Self {
+                self | other
+            }
+
+            fn difference(&self, other: &Self) -> Self {
+                self & !other
+            }
+
+            fn shift_down(&self, full: &Self) -> Self {
+                (self << 1) & full
+            }
+
+            fn shift_up(&self) -> Self {
+                self >> 1
+            }
+
+            fn pop_first(&mut self) -> Option<usize> {
+                if *self == 0 {
+                    None
+                } else {
+                    let row = self.trailing_zeros() as usize;
+                    *self &= *self - 1;
+                    Some(row)
+                }
+            }
+
+            fn len(&self) -> usize {
+                self.count_ones() as usize
+            }
+        }
+    };
+}
+
+impl_row_set_for_int!(u64);
+impl_row_set_for_int!(u128);
+
+/// A row set for boards of any size, stored as a little-endian list of 64-bit words.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct WideRowSet {
+    words: Vec<u64>,
+}
+
+impl RowSet for WideRowSet {
+    const MAX_SIDE_SIZE: Option<usize> = None;
+
+    fn empty(side_size: usize) -> Self {
+        WideRowSet { words: vec![0; side_size.div_ceil(64)] }
+    }
+
+    fn full(side_size: usize) -> Self {
+        let mut set = WideRowSet::empty(side_size);
+        for (i, word) in set.words.iter_mut().enumerate() {
+            *word = u64::full((side_size - i * 64).min(64));
+        }
+        set
+    }
+
+    fn insert(&mut self, row: usize) {
+        self.words[row / 64] |= 1 << (row % 64);
+    }
+
+    fn contains(&self, row: usize) -> bool {
+        self.words[row / 64].contains(row % 64)
+    }
+
+    fn union(&self, other: &Self) -> Self {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a | b).collect();
+        WideRowSet { words }
+    }
+
+    fn difference(&self, other: &Self) -> Self {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a & !b).collect();
+        WideRowSet { words }
+    }
+
+    fn shift_down(&self, full: &Self) -> Self {
+        let mut carry = 0;
+        let words = self.words.iter().zip(&full.words)
+            .map(|(word, full)| {
+                let shifted = (word << 1 | carry) & full;
+                carry = word >> 63;
+                shifted
+            })
+            .collect();
+        WideRowSet { words }
+    }
+
+    fn shift_up(&self) -> Self {
+        let mut carry = 0;
+        let mut words = self.words.clone();
+        for word in words.iter_mut().rev() {
+            let shifted = *word >> 1 | carry << 63;
+            carry = *word & 1;
+            *word = shifted;
+        }
+        WideRowSet { words }
+    }
+
+    fn pop_first(&mut self) -> Option<usize> {
+        self.words.iter_mut()
+            .enumerate()
+            .find(|(_, word)| **word != 0)
+            .and_then(|(i, word)| word.pop_first().map(|row| i * 64 + row))
+    }
+
+    fn len(&self) -> usize {
+        self.words.iter().map(|word| word.len()).sum()
+    }
+}
+
+/// Evaluates `$body` with `$S` standing for the narrowest [`RowSet`] that holds `$bits` rows, the
+/// one [`Backend::for_bits`](crate::search::Backend::for_bits) picks.
+macro_rules! with_row_set {
+    ($bits:expr, |$S:ident| $body:expr) => {
+        match $crate::search::Backend::for_bits($bits) {
+            $crate::search::Backend::Bits64 => {
+                type $S = u64;
+                $body
+            }
+            $crate::search::Backend::Bits128 => {
+                type $S = u128;
+                $body
+            }
+            _ => {
+                type $S = $crate::bitboard::WideRowSet;
+                $body
+            }
+        }
+    };
+}
+pub(crate) use with_row_set;
+
+/// The rows a queen placed in the current column would be attacked from.
+#[derive(Debug, Clone)]
+pub(crate) struct Frame<S> {
+    rows: S,
+    down_diagonals: S,
+    up_diagonals: S,
+}
+
+/// Restrictions on where a search may put its queens, on top of the queens not attacking each
+/// other.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub(crate) struct Constraints {
+    /// Queens that are on the board before the search starts. They must not attack each other
+    /// or stand on blocked squares, and every solution keeps them.
+    pub fixed: Vec<Queen>,
+    /// Squares no queen may stand on. Every solution carries them along.
+    pub blocked: Vec<Square>,
+    /// The geometry of the boards searched. Toroidal boards must be square.
+    pub geometry: Geometry,
+}
+
+impl Constraints {
+    fn is_empty(&self) -> bool {
+        self.fixed.is_empty() && self.blocked.is_empty() && self.geometry == Geometry::Flat
+    }
+}
+
+/// A search for placements of `queens` queens on boards `width` columns wide and `height` rows
+/// tall, going through the columns from left to right and putting at most one queen in each.
+/// Boards wider than they are tall are searched transposed, so that as few columns as possible go
+/// without a queen.
+#[derive(Debug, Clone)]
+pub(crate) struct BitSearch<S> {
+    /// The number of columns searched, after transposing.
+    width: usize,
+    /// The number of rows searched, after transposing.
+    height: usize,
+    queens: usize,
+    /// Whether the search's columns are the board's rows.
+    transposed: bool,
+    geometry: Geometry,
+    full: S,
+    /// For each column, the rows the constraints keep queens out of. `None` if there are no
+    /// constraints.
+    blocked: Option<Vec<S>>,
+    /// The blocked squares of the boards the search produces.
+    blocked_squares: Vec<Square>,
+}
+
+impl<S: RowSet> BitSearch<S> {
+    pub fn new(side_size: usize) -> BitSearch<S> {
+        BitSearch::with_constraints(side_size, &Constraints::default())
+    }
+
+    pub fn with_constraints(side_size: usize, constraints: &Constraints) -> BitSearch<S> {
+        BitSearch::with_shape(side_size, side_size, side_size, constraints)
+    }
+
+    /// A search for placements of `queens` queens on a `width`×`height` board. Fixed queens are
+    /// only supported if every column of the search gets a queen.
+    pub fn with_shape(width: usize, height: usize, queens: usize, constraints: &Constraints) -> BitSearch<S> {
+        let transposed = width > height;
+        let (width, height) = if transposed { (height, width) } else { (width, height) };
+        if let Some(max) = S::MAX_SIDE_SIZE {
+            assert!(height <= max, "bitmask backend supports boards up to {} wide, got {}", max, height);
+        }
+        assert!(constraints.fixed.is_empty() || queens == width, "fixed queens need a queen in every column");
+        assert!(constraints.geometry == Geometry::Flat || width == height, "toroidal boards must be square");
+
+        let full = S::full(height);
+        let blocked = if constraints.is_empty() {
+            None
+        } else if transposed {
+            let transpose = |squares: &[Square]| squares.iter().map(|s| Square::new(s.y, s.x)).collect();
+            let constraints = Constraints {
+                fixed: transpose(&constraints.fixed),
+                blocked: transpose(&constraints.blocked),
+                geometry: constraints.geometry,
+            };
+            Some(blocked_rows(width, height, &full, &constraints))
+        } else {
+            Some(blocked_rows(width, height, &full, constraints))
+        };
+        BitSearch {
+            width,
+            height,
+            queens,
+            transposed,
+            geometry: constraints.geometry,
+            full,
+            blocked,
+            blocked_squares: constraints.blocked.clone(),
+        }
+    }
+
+    /// The board with the queen of column `x`, if any, on row `rows[x]`.
+    fn board(&self, rows: &[Option<usize>]) -> Board {
+        let queens = rows.iter().enumerate().filter_map(|(x, &row)| row.map(|y| Queen::new(x, y)));
+        let board = if self.transposed {
+            Board::rect(self.height, self.width).with_added_queens(queens.map(|q| Queen::new(q.y, q.x)))
+        } else {
+            Board::rect(self.width, self.height).with_added_queens(queens)
+        };
+        board
+            .and_then(|board| board.with_geometry(self.geometry))
+            .and_then(|board| board.with_blocked_squares(self.blocked_squares.iter().copied()))
+            .expect("bitmask search produced row out of range")
+    }
+
+    pub fn root(&self) -> Frame<S> {
+        Frame {
+            rows: S::empty(self.height),
+            down_diagonals: S::empty(self.height),
+            up_diagonals: S::empty(self.height),
+        }
+    }
+
+    /// The rows of column `col` a queen can go in without being attacked, given the queens placed
+    /// in the columns before it.
+    pub fn free_rows(&self, frame: &Frame<S>, col: usize) -> S {
+        let attacked = frame.rows.union(&frame.down_diagonals).union(&frame.up_diagonals);
+        let free_rows = self.full.difference(&attacked);
+        match &self.blocked {
+            Some(blocked) => free_rows.difference(&blocked[col]),
+            None => free_rows,
+        }
+    }
+
+    /// The frame for the next column, after placing a queen on `row` of the current one.
+    pub fn place(&self, frame: &Frame<S>, row: usize) -> Frame<S> {
+        let mut rows = frame.rows.clone();
+        let mut down_diagonals = frame.down_diagonals.clone();
+        let mut up_diagonals = frame.up_diagonals.clone();
+        rows.insert(row);
+        down_diagonals.insert(row);
+        up_diagonals.insert(row);
+        self.advance(rows, &down_diagonals, &up_diagonals)
+    }
+
+    /// The frame for the next column, leaving the current one empty.
+    fn skip(&self, frame: &Frame<S>) -> Frame<S> {
+        self.advance(frame.rows.clone(), &frame.down_diagonals, &frame.up_diagonals)
+    }
+
+    /// Moves the diagonals on to the next column.
+    fn advance(&self, rows: S, down_diagonals: &S, up_diagonals: &S) -> Frame<S> {
+        match self.geometry {
+            Geometry::Flat => Frame {
+                rows,
+                down_diagonals: down_diagonals.shift_down(&self.full),
+                up_diagonals: up_diagonals.shift_up(),
+            },
+            Geometry::Toroidal => Frame {
+                rows,
+                down_diagonals: down_diagonals.rotate_down(&self.full, self.height),
+                up_diagonals: up_diagonals.rotate_up(self.height),
+            },
+        }
+    }
+
+    /// The frame for the column after the queens on `rows`, which must already be valid.
+    pub fn frame_after(&self, rows: &[Option<usize>]) -> Frame<S> {
+        rows.iter().fold(self.root(), |frame, &row| match row {
+            Some(row) => self.place(&frame, row),
+            None => self.skip(&frame),
+        })
+    }
+
+    /// How many queens are left to place after the ones on `rows`.
+    fn queens_left(&self, rows: &[Option<usize>]) -> usize {
+        self.queens - rows.iter().flatten().count()
+    }
+
+    /// Whether column `col` can be left empty with `queens_left` queens still to place.
+    fn can_skip(&self, col: usize, queens_left: usize) -> bool {
+        self.width - col > queens_left
+    }
+
+    /// Whether the columns from `col` onwards can still fit `queens_left` queens.
+    fn has_room(&self, col: usize, queens_left: usize) -> bool {
+        self.width - col >= queens_left
+    }
+
+    /// Every valid placement of the first `depth` columns, or of every column on narrower boards,
+    /// as the rows of their queens. Placements that already hold every queen stop short. The
+    /// parallel searches split the work along these.
+    pub fn prefixes(&self, depth: usize) -> Vec<Vec<Option<usize>>> {
+        let mut prefixes = vec![];
+        self.collect_prefixes(&self.root(), &mut vec![], depth.min(self.width), &mut prefixes);
+        prefixes
+    }
+
+    fn collect_prefixes(
+        &self,
+        frame: &Frame<S>,
+        rows: &mut Vec<Option<usize>>,
+        depth: usize,
+        prefixes: &mut Vec<Vec<Option<usize>>>,
+    ) {
+        let queens_left = self.queens_left(rows);
+        if rows.len() == depth || queens_left == 0 {
+            prefixes.push(rows.clone());
+            return;
+        }
+        if !self.has_room(rows.len(), queens_left) {
+            return;
+        }
+
+        let mut free_rows = self.free_rows(frame, rows.len());
+        while let Some(row) = free_rows.pop_first() {
+            rows.push(Some(row));
+            self.collect_prefixes(&self.place(frame, row), rows, depth, prefixes);
+            rows.pop();
+        }
+        if self.can_skip(rows.len(), queens_left) {
+            rows.push(None);
+            self.collect_prefixes(&self.skip(frame), rows, depth, prefixes);
+            rows.pop();
+        }
+    }
+
+    /// Calls `f` with every solution, searching the first two columns in parallel.
+    pub fn for_each_solution<F>(&self, f: &F)
+        where F: Fn(&Board) + Sync
+    {
+        self.prefixes(PARALLEL_DEPTH).into_par_iter().for_each(|mut rows| {
+            let frame = self.frame_after(&rows);
+            let queens_left = self.queens_left(&rows);
+            self.for_each_completion(&frame, &mut rows, queens_left, f)
+        });
+    }
+
+    fn for_each_completion<F>(&self, frame: &Frame<S>, rows: &mut Vec<Option<usize>>, queens_left: usize, f: &F)
+        where F: Fn(&Board)
+    {
+        if queens_left == 0 {
+            f(&self.board(rows));
+            return;
+        }
+        if !self.has_room(rows.len(), queens_left) {
+            return;
+        }
+
+        let mut free_rows = self.free_rows(frame, rows.len());
+        while let Some(row) = free_rows.pop_first() {
+            rows.push(Some(row));
+            self.for_each_completion(&self.place(frame, row), rows, queens_left - 1, f);
+            rows.pop();
+        }
+        if self.can_skip(rows.len(), queens_left) {
+            rows.push(None);
+            self.for_each_completion(&self.skip(frame), rows, queens_left, f);
+            rows.pop();
+        }
+    }
+
+    /// The number of solutions, counted without building any boards.
+    pub fn count_solutions(&self, mode: SearchMode) -> usize {
+        self.prefixes(PARALLEL_DEPTH).into_par_iter()
+            .map(|rows| self.count_prefix_solutions(&rows, mode))
+            .sum()
+    }
+
+    /// The number of solutions starting with the queens on `rows`, one of the
+    /// [`prefixes`](BitSearch::prefixes), counted the way [`count_solutions`](BitSearch::count_solutions)
+    /// counts them: a prefix that `mode` leaves out has none, and one it mirrors counts twice.
+    pub fn count_prefix_solutions(&self, rows: &[Option<usize>], mode: SearchMode) -> usize {
+        let weight = self.prefix_weight(rows, mode);
+        if weight == 0 {
+            return 0;
+        }
+        let frame = self.frame_after(rows);
+        let count = if self.queens == self.width {
+            self.count_full_completions(&frame, rows.len())
+        } else {
+            self.count_completions(&frame, rows.len(), self.queens_left(rows))
+        };
+        weight * count
+    }
+
+    /// How many solutions each solution starting with the queens on `rows` stands for.
+    /// Constraints generally aren't symmetric, so a constrained search always counts
+    /// exhaustively.
+    pub fn prefix_weight(&self, rows: &[Option<usize>], mode: SearchMode) -> usize {
+        let mode = if self.blocked.is_some() { SearchMode::Exhaustive } else { mode };
+        match rows.first() {
+            Some(&Some(first_row)) => mode.weight(self.height, first_row),
+            _ => 1,
+        }
+    }
+
+    /// [`count_completions`](BitSearch::count_completions) for searches with a queen in every
+    /// column, which never skip one. Leaving out the checks for skipping makes the plain
+    /// n-queens count about 5% faster.
+    fn count_full_completions(&self, frame: &Frame<S>, col: usize) -> usize {
+        if col == self.width {
+            return 1;
+        }
+
+        let mut count = 0;
+        let mut free_rows = self.free_rows(frame, col);
+        while let Some(row) = free_rows.pop_first() {
+            count += self.count_full_completions(&self.place(frame, row), col + 1);
+        }
+        count
+    }
+
+    fn count_completions(&self, frame: &Frame<S>, col: usize, queens_left: usize) -> usize {
+        if queens_left == 0 {
+            return 1;
+        }
+        if !self.has_room(col, queens_left) {
+            return 0;
+        }
+
+        let mut count = 0;
+        let mut free_rows = self.free_rows(frame, col);
+        while let Some(row) = free_rows.pop_first() {
+            count += self.count_completions(&self.place(frame, row), col + 1, queens_left - 1);
+        }
+        if self.can_skip(col, queens_left) {
+            count += self.count_completions(&self.skip(frame), col + 1, queens_left);
+        }
+        count
+    }
+
+    /// The number of valid placements of every number of queens, from none up to one in every
+    /// column, whatever the search's own number of queens. Each choice for the first column is
+    /// searched in parallel.
+    pub fn count_by_queens(&self) -> Vec<usize> {
+        let firsts = if self.width == 0 {
+            vec![vec![]]
+        } else {
+            let mut free_rows = self.free_rows(&self.root(), 0);
+            let mut firsts = vec![vec![None]];
+            while let Some(row) = free_rows.pop_first() {
+                firsts.push(vec![Some(row)]);
+            }
+            firsts
+        };
+        firsts.into_par_iter()
+            .map(|rows| {
+                let mut counts = vec![0; self.width + 1];
+                let placed = rows.iter().flatten().count();
+                self.count_by_queens_from(&self.frame_after(&rows), rows.len(), placed, &mut counts);
+                counts
+            })
+            .reduce(|| vec![0; self.width + 1], |a, b| a.iter().zip(&b).map(|(a, b)| a + b).collect())
+    }
+
+    fn count_by_queens_from(&self, frame: &Frame<S>, col: usize, placed: usize, counts: &mut [usize]) {
+        if col == self.width {
+            counts[placed] += 1;
+            return;
+        }
+        let mut free_rows = self.free_rows(frame, col);
+        while let Some(row) = free_rows.pop_first() {
+            self.count_by_queens_from(&self.place(frame, row), col + 1, placed + 1, counts);
+        }
+        self.count_by_queens_from(&self.skip(frame), col + 1, placed, counts);
+    }
+
+    /// The first solution in row order, found sequentially.
+    pub fn first_solution(&self) -> Option<Board> {
+        self.clone().solutions(vec![]).next()
+    }
+
+    /// Lazily walks every solution that starts with the queens on `prefix`, in row order unless
+    /// the board is transposed.
+    pub fn solutions(self, prefix: Vec<Option<usize>>) -> BitSolutions<S> {
+        let frame = self.frame_after(&prefix);
+        let queens_left = self.queens_left(&prefix);
+        let level = self.level(frame, prefix.len(), queens_left);
+        BitSolutions {
+            complete_prefix: queens_left == 0,
+            prefix_len: prefix.len(),
+            stack: vec![level],
+            rows: prefix,
+            queens_left,
+            search: self,
+        }
+    }
+
+    /// The choices for column `col`, reached with `frame` and `queens_left` queens still to place.
+    fn level(&self, frame: Frame<S>, col: usize, queens_left: usize) -> Level<S> {
+        if queens_left == 0 || !self.has_room(col, queens_left) {
+            return Level { frame, free_rows: S::empty(self.height), skip: false };
+        }
+        let free_rows = self.free_rows(&frame, col);
+        Level { frame, free_rows, skip: self.can_skip(col, queens_left) }
+    }
+}
+
+/// For each column, the rows that `constraints` keep queens out of: the blocked squares, every
+/// row but its own in a column with a fixed queen, and every row a fixed queen attacks elsewhere.
+fn blocked_rows<S: RowSet>(width: usize, height: usize, full: &S, constraints: &Constraints) -> Vec<S> {
+    let mut blocked = vec![S::empty(height); width];
+    for square in &constraints.blocked {
+        blocked[square.x].insert(square.y);
+    }
+    for fixed in &constraints.fixed {
+        for (x, rows) in blocked.iter_mut().enumerate() {
+            if x == fixed.x {
+                let mut row = S::empty(height);
+                row.insert(fixed.y);
+                *rows = rows.union(&full.difference(&row));
+                continue;
+            }
+            let distance = fixed.x.abs_diff(x);
+            rows.insert(fixed.y);
+            match constraints.geometry {
+                Geometry::Flat => {
+                    if fixed.y >= distance {
+                        rows.insert(fixed.y - distance);
+                    }
+                    if fixed.y + distance < height {
+                        rows.insert(fixed.y + distance);
+                    }
+                }
+                Geometry::Toroidal => {
+                    rows.insert((fixed.y + height - distance % height) % height);
+                    rows.insert((fixed.y + distance) % height);
+                }
+            }
+        }
+    }
+    blocked
+}
+
+/// How many columns the parallel searches split the board along.
+pub(crate) const PARALLEL_DEPTH: usize = 2;
+
+/// A column on the stack of [`BitSolutions`].
+#[derive(Debug, Clone)]
+struct Level<S> {
+    frame: Frame<S>,
+    /// The rows left to try.
+    free_rows: S,
+    /// Whether leaving the column empty is still to be tried.
+    skip: bool,
+}
+
+/// A depth-first search that stops after every solution it finds.
+#[derive(Debug, Clone)]
+pub(crate) struct BitSolutions<S> {
+    search: BitSearch<S>,
+    /// The columns past the prefix that are being tried.
+    stack: Vec<Level<S>>,
+    /// The rows of the queens placed so far, including the prefix.
+    rows: Vec<Option<usize>>,
+    queens_left: usize,
+    prefix_len: usize,
+    /// Set if the prefix is a solution by itself and hasn't been yielded yet.
+    complete_prefix: bool,
+}
+
+impl<S: RowSet> Iterator for BitSolutions<S> {
+    type Item = Board;
+
+    fn next(&mut self) -> Option<Board> {
+        if self.complete_prefix {
+            self.complete_prefix = false;
+            self.stack.clear();
+            return Some(self.search.board(&self.rows));
+        }
+
+        while let Some(level) = self.stack.last_mut() {
+            let row = match level.free_rows.pop_first() {
+                Some(row) => Some(row),
+                None if level.skip => {
+                    level.skip = false;
+                    None
+                }
+                None => {
+                    self.stack.pop();
+                    if self.rows.len() > self.prefix_len && self.rows.pop().flatten().is_some() {
+                        self.queens_left += 1;
+                    }
+                    continue;
+                }
+            };
+
+            let child = match row {
+                Some(row) => self.search.place(&level.frame, row),
+                None => self.search.skip(&level.frame),
+            };
+            self.rows.push(row);
+            if row.is_some() {
+                self.queens_left -= 1;
+                if self.queens_left == 0 {
+                    let board = self.search.board(&self.rows);
+                    self.rows.pop();
+                    self.queens_left += 1;
+                    return Some(board);
+                }
+            }
+            let level = self.search.level(child, self.rows.len(), self.queens_left);
+            self.stack.push(level);
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows_of<S: RowSet>(mut set: S) -> Vec<usize> {
+        let mut rows = vec![];
+        while let Some(row) = set.pop_first() {
+            rows.push(row);
+        }
+        rows
+    }
+
+    #[test]
+    fn test_wide_row_set() {
+        let full = WideRowSet::full(130);
+        assert_eq!(rows_of(full.clone()), (0..130).collect::<Vec<_>>());
+
+        let mut set = WideRowSet::empty(130);
+        set.insert(0);
+        set.insert(63);
+        set.insert(129);
+        assert_eq!(rows_of(set.shift_down(&full)), vec![1, 64]);
+        assert_eq!(rows_of(set.shift_up()), vec![62, 128]);
+        assert_eq!(rows_of(full.difference(&set)).len(), 127);
+    }
+
+    #[test]
+    fn test_solutions_iter() {
+        let search = BitSearch::<u64>::new(8);
+        let solutions: Vec<_> = search.clone().solutions(vec![]).collect();
+        assert_eq!(solutions.len(), 92);
+        assert!(solutions.windows(2).all(|w| w[0].queens() < w[1].queens()));
+        assert_eq!(solutions[0], search.first_solution().unwrap());
+
+        let from_prefix: Vec<_> = search.clone().solutions(vec![Some(0), Some(4)]).collect();
+        let expected: Vec<_> = solutions.iter()
+            .filter(|b| b.queens()[0].y == 0 && b.queens()[1].y == 4)
+            .cloned()
+            .collect();
+        assert_eq!(from_prefix, expected);
+
+        let solution = [0, 4, 7, 5, 2, 6, 1, 3].iter().map(|&row| Some(row)).collect();
+        assert_eq!(search.solutions(solution).count(), 1);
+        assert_eq!(BitSearch::<u64>::new(0).solutions(vec![]).count(), 1);
+        assert_eq!(BitSearch::<u64>::new(3).solutions(vec![]).count(), 0);
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        for side_size in 1..=9 {
+            let count = BitSearch::<u64>::new(side_size).count_solutions(SearchMode::Exhaustive);
+            assert_eq!(BitSearch::<u128>::new(side_size).count_solutions(SearchMode::Exhaustive), count);
+            assert_eq!(BitSearch::<WideRowSet>::new(side_size).count_solutions(SearchMode::Exhaustive), count);
+            for &mode in &[SearchMode::Exhaustive, SearchMode::Mirror] {
+                assert_eq!(BitSearch::<u64>::new(side_size).count_solutions(mode), count);
+                assert_eq!(BitSearch::<WideRowSet>::new(side_size).count_solutions(mode), count);
+            }
+        }
+    }
+}
@@ -0,0 +1,484 @@
+use rayon::prelude::*;
+
+/// A queen's position on a board. `x` is the column and `y` is the row, both counted from the
+/// top-left corner.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Queen {
+    pub x: usize,
+    pub y: usize,
+}
+
+/// A square of the board, addressed the same way as a queen standing on it.
+pub type Square = Queen;
+
+/// How a board's lines behave at its edges.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Geometry {
+    /// Rows, columns and diagonals end at the edges of the board.
+    #[default]
+    Flat,
+    /// The board wraps around like a torus: a line leaving one edge comes back in at the
+    /// opposite one, so on an `n`×`n` board the diagonals are taken mod `n` and every line has
+    /// `n` squares. Only square boards can be toroidal. `n` queens fit on one exactly when `n` is
+    /// coprime to 6 (Pólya, 1918).
+    Toroidal,
+}
+
+/// A rectangular board holding any number of queens, with some of its squares possibly blocked so
+/// that no queen may stand on them. Blocked squares don't stop queens from attacking across them,
+/// but pawns do; see [`Board::is_attacking`]. Boards are flat unless given another
+/// [`Geometry`].
+///
+/// Queens, pawns and blocked squares are kept sorted by column, so two boards with the same
+/// placement always compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Board {
+    queens: Vec<Queen>,
+    pawns: Vec<Square>,
+    blocked: Vec<Square>,
+    width: usize,
+    height: usize,
+    geometry: Geometry,
+}
+
+impl Board {
+    /// Creates an empty `side_size`×`side_size` board.
+    pub fn new(side_size: usize) -> Board {
+        Board::rect(side_size, side_size)
+    }
+
+    /// Creates an empty board `width` columns wide and `height` rows tall.
+    pub fn rect(width: usize, height: usize) -> Board {
+        Board {
+            queens: vec![],
+            pawns: vec![],
+            blocked: vec![],
+            width,
+            height,
+            geometry: Geometry::Flat,
+        }
+    }
+
+    /// Creates a `side_size`×`side_size` board with the given queens already placed, or `None` if
+    /// any queen lies off the board or two queens share a square. The placement doesn't have to
+    /// be valid.
+    pub fn with_queens(side_size: usize, queens: impl IntoIterator<Item=Queen>) -> Option<Board> {
+        Board::new(side_size).with_added_queens(queens)
+    }
+
+    /// Returns the board with `queens` added, or `None` if any of them lies off the board or
+    /// shares a square with another queen. The placement doesn't have to be valid.
+    pub fn with_added_queens(mut self, queens: impl IntoIterator<Item=Queen>) -> Option<Board> {
+        self.queens.extend(queens);
+        self.queens.sort();
+        let in_bounds = self.queens.iter().all(|&q| self.contains(q));
+        let distinct = self.queens.windows(2).all(|w| w[0] != w[1]);
+        if in_bounds && distinct {
+            Some(self)
+        } else {
+            None
+        }
+    }
+
+    /// Creates a `rows.len()`-wide board with the queen of column `x` on row `rows[x]`, or `None`
+    /// if a row lies off the board. The placement doesn't have to be valid.
+    pub fn from_rows(rows: &[usize]) -> Option<Board> {
+        let side_size = rows.len();
+        if rows.iter().all(|&row| row < side_size) {
+            let queens = rows.iter().enumerate().map(|(x, &y)| Queen::new(x, y)).collect();
+            Some(Board { queens, ..Board::new(side_size) })
+        } else {
+            None
+        }
+    }
+
+    /// Returns the board with pawns added on `squares`, or `None` if any of them lies off the
+    /// board. A pawn on a queen's square makes the board invalid.
+    pub fn with_pawns(mut self, squares: impl IntoIterator<Item=Square>) -> Option<Board> {
+        self.pawns.extend(squares);
+        if !self.pawns.iter().all(|&s| self.contains(s)) {
+            return None;
+        }
+        self.pawns.sort();
+        self.pawns.dedup();
+        Some(self)
+    }
+
+    /// Returns the board with `squares` blocked as well, or `None` if any of them lies off the
+    /// board. Queens already on the board stay put, even on squares that are now blocked, but
+    /// the board is no longer valid then.
+    pub fn with_blocked_squares(mut self, squares: impl IntoIterator<Item=Square>) -> Option<Board> {
+        self.blocked.extend(squares);
+        if !self.blocked.iter().all(|&s| self.contains(s)) {
+            return None;
+        }
+        self.blocked.sort();
+        self.blocked.dedup();
+        Some(self)
+    }
+
+    /// Returns the board with the given geometry, or `None` if it's toroidal and the board isn't
+    /// square. The pieces stay where they are, but whether they attack each other can change.
+    pub fn with_geometry(mut self, geometry: Geometry) -> Option<Board> {
+        if geometry == Geometry::Toroidal && !self.is_square() {
+            return None;
+        }
+        self.geometry = geometry;
+        Some(self)
+    }
+
+    /// The length of the board's sides.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board isn't square.
+    pub fn side_size(&self) -> usize {
+        assert!(self.is_square(), "{}x{} board isn't square", self.width, self.height);
+        self.width
+    }
+
+    /// The number of columns.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of rows.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn is_square(&self) -> bool {
+        self.width == self.height
+    }
+
+    pub fn geometry(&self) -> Geometry {
+        self.geometry
+    }
+
+    /// The indices of the two diagonals through `square`, as given by [`Queen::sw_diagonal`] and
+    /// [`Queen::se_diagonal`] on flat boards and taken mod the side size on toroidal ones.
+    pub(crate) fn diagonals(&self, square: Square) -> (usize, usize) {
+        match self.geometry {
+            Geometry::Flat => (square.sw_diagonal(self.height), square.se_diagonal(self.height)),
+            Geometry::Toroidal => {
+                let n = self.width;
+                ((n + square.x - square.y) % n, (square.x + square.y) % n)
+            }
+        }
+    }
+
+    /// Whether `square` lies on the board.
+    fn contains(&self, square: Square) -> bool {
+        square.x < self.width && square.y < self.height
+    }
+
+    /// The queens on the board, sorted by column and then by row.
+    pub fn queens(&self) -> &[Queen] {
+        &self.queens
+    }
+
+    /// The squares holding pawns, sorted by column and then by row.
+    pub fn pawns(&self) -> &[Square] {
+        &self.pawns
+    }
+
+    pub fn has_pawn(&self, square: Square) -> bool {
+        self.pawns.binary_search(&square).is_ok()
+    }
+
+    /// The squares no queen may stand on, sorted by column and then by row.
+    pub fn blocked_squares(&self) -> &[Square] {
+        &self.blocked
+    }
+
+    pub fn is_blocked(&self, square: Square) -> bool {
+        self.blocked.binary_search(&square).is_ok()
+    }
+
+    /// Renders the board with `QQ` for queens, `PP` for pawns, `##` for blocked squares and `__`
+    /// for empty ones, one line per row.
+    pub fn get_board_string(&self) -> String {
+        let mut string = String::new();
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.queens.contains(&Queen::new(x, y)) {
+                    string += "QQ";
+                } else if self.has_pawn(Square::new(x, y)) {
+                    string += "PP";
+                } else if self.is_blocked(Square::new(x, y)) {
+                    string += "##";
+                } else {
+                    string += "__";
+                }
+            }
+            string += "\n";
+        }
+        string
+    }
+
+    /// Whether the board holds one queen per column, or per row if it's wider than it's tall.
+    /// Only meaningful for valid boards.
+    pub fn is_complete(&self) -> bool {
+        self.queens.len() == self.width.min(self.height)
+    }
+
+    /// Every valid board made by adding a queen to column `col`.
+    pub fn valid_direct_children_with_queen_in_col(&self, col: usize) -> impl '_ + Iterator<Item=Board> {
+        (0..self.height)
+            .map(move |row| Queen::new(col, row))
+            .filter_map(move |queen| self.try_insert_queen(queen))
+    }
+
+    /// Parallel version of [`valid_direct_children_with_queen_in_col`](Board::valid_direct_children_with_queen_in_col).
+    pub fn parallel_valid_direct_children_with_queen_in_col(&self, col: usize) -> impl '_ + ParallelIterator<Item=Board> {
+        (0..self.height).into_par_iter()
+            .map(move |row| Queen::new(col, row))
+            .filter_map(move |queen| self.try_insert_queen(queen))
+    }
+
+    /// Returns a copy of the board with `queen` added, or `None` if the square is taken or
+    /// blocked or the new queen would attack another one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `queen` lies off the board.
+    pub fn try_insert_queen(&self, queen: Queen) -> Option<Board> {
+        assert!(queen.x < self.width);
+        assert!(queen.y < self.height);
+
+        if self.is_blocked(queen) || self.has_pawn(queen) {
+            return None;
+        }
+        for q in &self.queens {
+            if *q == queen {
+                return None;
+            }
+        }
+
+        let mut new_board = self.clone();
+        new_board.queens.push(queen);
+        new_board.queens.sort();
+        if new_board.is_valid() {
+            Some(new_board)
+        } else {
+            None
+        }
+    }
+
+    /// Whether no two queens on the board attack each other and none stands on a blocked square
+    /// or a pawn.
+    pub fn is_valid(&self) -> bool {
+        if !self.blocked.is_empty() && self.queens.iter().any(|&q| self.is_blocked(q)) {
+            return false;
+        }
+        if !self.pawns.is_empty() {
+            return self.is_valid_with_pawns();
+        }
+
+        use std::cell::RefCell;
+        thread_local!{
+            static BOOL_FIELD: RefCell<Vec<bool>> = const { RefCell::new(Vec::new()) };
+        }
+        BOOL_FIELD.with(|bool_field| {
+            let mut bool_field = bool_field.borrow_mut();
+            let diagonals = self.width + self.height;
+            let needed_size = 2 * diagonals + self.width + self.height;
+            if bool_field.len() < needed_size {
+                *bool_field = vec![false; needed_size];
+            } else {
+                for b in &mut *bool_field {
+                    *b = false;
+                }
+            }
+            let mut bool_field_slice = &mut bool_field[..];
+            let (s, r) = bool_field_slice.split_at_mut(self.height);
+            bool_field_slice = r;
+            let occupied_rows = s;
+            let (s, r) = bool_field_slice.split_at_mut(self.width);
+            bool_field_slice = r;
+            let occupied_cols = s;
+            let (s, r) = bool_field_slice.split_at_mut(diagonals);
+            bool_field_slice = r;
+            let occupied_sw_diagonals = s;
+            let (s, _) = bool_field_slice.split_at_mut(diagonals);
+            let occupied_se_diagonals = s;
+
+            for q in &self.queens {
+                let row = q.row();
+                let col = q.col();
+                let (sw_diagonal, se_diagonal) = self.diagonals(*q);
+
+                if occupied_rows[row] {
+                    return false;
+                } else {
+                    occupied_rows[row] = true;
+                }
+                if occupied_cols[col] {
+                    return false;
+                } else {
+                    occupied_cols[col] = true;
+                }
+                if occupied_sw_diagonals[sw_diagonal] {
+                    return false;
+                } else {
+                    occupied_sw_diagonals[sw_diagonal] = true;
+                }
+                if occupied_se_diagonals[se_diagonal] {
+                    return false;
+                } else {
+                    occupied_se_diagonals[se_diagonal] = true;
+                }
+            }
+
+            true
+        })
+    }
+}
+
+impl Queen {
+    pub fn new(x: usize, y: usize) -> Queen {
+        Queen{ x, y }
+    }
+
+    pub fn row(&self) -> usize {
+        self.y
+    }
+
+    pub fn col(&self) -> usize {
+        self.x
+    }
+
+    /// Index of the diagonal running from the top-left to the bottom-right through this queen,
+    /// in `0..board_width + board_height - 1`.
+    pub fn sw_diagonal(&self, board_height: usize) -> usize {
+        board_height + self.x - self.y - 1
+    }
+
+    /// Index of the diagonal running from the bottom-left to the top-right through this queen,
+    /// in `0..board_width + board_height - 1`.
+    pub fn se_diagonal(&self, _board_height: usize) -> usize {
+        self.x + self.y
+        // board_height + self.y - self.x - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_sw_diagonal() {
+        let bs = 8;
+        assert_eq!(Queen::new(0, 1).sw_diagonal(bs), 6);
+        assert_eq!(Queen::new(0, 7).sw_diagonal(bs), 0);
+
+        assert_eq!(Queen::new(0, 0).sw_diagonal(bs), 7);
+        assert_eq!(Queen::new(1, 1).sw_diagonal(bs), 7);
+        assert_eq!(Queen::new(2, 2).sw_diagonal(bs), 7);
+        assert_eq!(Queen::new(1, 0).sw_diagonal(bs), 8);
+        assert_eq!(Queen::new(2, 0).sw_diagonal(bs), 9);
+        assert_eq!(Queen::new(7, 0).sw_diagonal(bs), 14);
+    }
+
+    #[test]
+    fn test_with_queens() {
+        let board = Board::with_queens(4, vec![Queen::new(2, 0), Queen::new(0, 1)]).unwrap();
+        assert_eq!(board.queens(), &[Queen::new(0, 1), Queen::new(2, 0)]);
+        assert!(board.is_valid());
+        assert!(!board.is_complete());
+
+        assert!(Board::with_queens(4, vec![Queen::new(4, 0)]).is_none());
+        assert!(Board::with_queens(4, vec![Queen::new(1, 1), Queen::new(1, 1)]).is_none());
+        assert!(!Board::with_queens(4, vec![Queen::new(0, 0), Queen::new(3, 3)]).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_blocked_squares() {
+        let board = Board::new(4)
+            .with_blocked_squares(vec![Square::new(1, 3), Square::new(0, 1), Square::new(1, 3)])
+            .unwrap();
+        assert_eq!(board.blocked_squares(), &[Square::new(0, 1), Square::new(1, 3)]);
+        assert!(board.is_blocked(Square::new(1, 3)));
+        assert!(!board.is_blocked(Square::new(3, 1)));
+        assert!(board.try_insert_queen(Queen::new(0, 1)).is_none());
+
+        let board = board.try_insert_queen(Queen::new(0, 2)).unwrap();
+        assert!(board.is_valid());
+        assert_eq!(board.get_board_string(), "________\n##______\nQQ______\n__##____\n");
+        assert!(!board.with_blocked_squares(vec![Square::new(0, 2)]).unwrap().is_valid());
+
+        assert!(Board::new(4).with_blocked_squares(vec![Square::new(0, 4)]).is_none());
+    }
+
+    #[test]
+    fn test_rect() {
+        let board = Board::rect(5, 3);
+        assert_eq!((board.width(), board.height()), (5, 3));
+        assert!(!board.is_square());
+        assert!(board.try_insert_queen(Queen::new(4, 2)).is_some());
+
+        let board = board.with_added_queens(vec![Queen::new(0, 0), Queen::new(3, 1), Queen::new(1, 2)]).unwrap();
+        assert!(board.is_valid() && board.is_complete());
+        assert_eq!(board.get_board_string(), "QQ________\n______QQ__\n__QQ______\n");
+        assert!(board.try_insert_queen(Queen::new(3, 0)).is_none());
+        assert!(!board.clone().with_added_queens(vec![Queen::new(4, 2)]).unwrap().is_valid());
+        assert!(board.clone().with_added_queens(vec![Queen::new(0, 3)]).is_none());
+        assert!(board.with_added_queens(vec![Queen::new(1, 2)]).is_none());
+
+        let tall = Board::rect(2, 4).with_added_queens(vec![Queen::new(0, 3), Queen::new(1, 0)]).unwrap();
+        assert!(tall.is_valid() && tall.is_complete());
+        assert!(!Board::rect(2, 4).with_added_queens(vec![Queen::new(0, 3), Queen::new(1, 2)]).unwrap().is_valid());
+    }
+
+    #[test]
+    fn test_toroidal() {
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(2, 1), Queen::new(4, 2)]).unwrap();
+        assert!(board.is_valid());
+        let board = board.with_geometry(Geometry::Toroidal).unwrap();
+        assert_eq!(board.geometry(), Geometry::Toroidal);
+        assert_eq!(board.diagonals(Queen::new(4, 2)), (2, 1));
+        assert!(board.is_valid());
+        // (1, 4) and (4, 2) lie on the same diagonal, which leaves through the bottom edge.
+        assert!(board.try_insert_queen(Queen::new(1, 4)).is_none());
+        assert!(board.try_insert_queen(Queen::new(1, 3)).is_some());
+        assert!(!Board::with_queens(5, vec![Queen::new(0, 1), Queen::new(4, 0)]).unwrap()
+            .with_geometry(Geometry::Toroidal).unwrap()
+            .is_valid());
+
+        assert!(Board::rect(5, 3).with_geometry(Geometry::Toroidal).is_none());
+        assert!(Board::rect(5, 3).with_geometry(Geometry::Flat).is_some());
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_side_size_of_rect() {
+        Board::rect(5, 3).side_size();
+    }
+
+    // #[test]
+    // fn test_se_diagonal() {
+    //     let bs = 8;
+    //     assert_eq!(Queen::new(0, 1).se_diagonal(bs), 8);
+    //     assert_eq!(Queen::new(0, 7).se_diagonal(bs), 14);
+
+    //     assert_eq!(Queen::new(0, 0).se_diagonal(bs), 7);
+    //     assert_eq!(Queen::new(1, 1).se_diagonal(bs), 7);
+    //     assert_eq!(Queen::new(2, 2).se_diagonal(bs), 7);
+    //     assert_eq!(Queen::new(1, 0).se_diagonal(bs), 6);
+    //     assert_eq!(Queen::new(2, 0).se_diagonal(bs), 5);
+    //     assert_eq!(Queen::new(7, 0).se_diagonal(bs), 0);
+    //     assert_eq!(Queen::new(6, 1).se_diagonal(bs), 0);
+    // }
+}
+
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+// [][][][][][][][]
+
+// 01234567
+// 12345678
+// 23456789
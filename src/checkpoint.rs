@@ -0,0 +1,346 @@
+//! Counting solutions in a way that survives being stopped. The search is split into work units,
+//! one for each valid placement of the queens in the first few columns, and a [`Checkpoint`]
+//! records the count of every unit as it finishes. Written out and read back in, it lets a count
+//! that takes hours carry on where it left off instead of starting over.
+//!
+//! Checkpoints are written as text, starting with a header line and the board size and depth,
+//! followed by one line for each finished unit giving the rows of its queens, counted from 0 and
+//! separated by commas, and the number of solutions it stands for:
+//!
+//! ```text
+//! nqueens checkpoint 1
+//! size 8
+//! depth 2
+//! done 0,2 0
+//! done 0,3 0
+//! done 0,4 2
+//! ```
+//!
+//! Units are named by their rows rather than their position in the search, so a checkpoint stays
+//! good as long as the board size and depth match. As in [`SearchMode::Mirror`], the units whose
+//! first queen is in the bottom half of the board are left out and the ones in the top half count
+//! twice.
+
+use crate::bitboard::{with_row_set, BitSearch, RowSet};
+use crate::search::SearchMode;
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+use std::sync::Mutex;
+
+const HEADER: &str = "nqueens checkpoint 1";
+
+/// The progress of a count of the solutions of the n-queens problem.
+///
+/// ```
+/// use nqueens::Checkpoint;
+///
+/// let mut checkpoint = Checkpoint::new(10, 2);
+/// let mut saved = vec![];
+/// let count = checkpoint.run(|checkpoint| {
+///     saved.push(checkpoint.to_string());
+///     Ok::<(), ()>(())
+/// });
+/// assert_eq!(count, Ok(724));
+/// assert_eq!(saved.len(), checkpoint.units());
+///
+/// // Picking up from the checkpoint saved halfway through finishes the count.
+/// let mut resumed: Checkpoint = saved[saved.len() / 2].parse().unwrap();
+/// assert!(resumed.count().is_none());
+/// assert_eq!(resumed.run(|_| Ok::<(), ()>(())), Ok(724));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Checkpoint {
+    side_size: usize,
+    depth: usize,
+    /// The rows of the queens of every work unit, in search order.
+    units: Vec<Vec<Option<usize>>>,
+    /// The number of solutions each finished unit stands for, by index into `units`.
+    done: Vec<Option<usize>>,
+}
+
+/// Returned for text that isn't a checkpoint. Lines are counted from 1, as in a text editor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseCheckpointError {
+    /// The first line isn't the checkpoint header.
+    BadHeader,
+    /// A line that isn't a `size`, `depth` or `done` line, or is one with the wrong values.
+    BadLine { line: usize, found: String },
+    /// There's no `size` or no `depth` line before the first `done` line.
+    Missing { field: &'static str },
+    /// A `done` line for something that isn't one of the work units.
+    UnknownUnit { line: usize, unit: String },
+    /// A second `done` line for the same work unit.
+    DuplicateUnit { line: usize, unit: String },
+}
+
+impl fmt::Display for ParseCheckpointError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseCheckpointError::BadHeader => write!(
+                f,
+                "not a checkpoint, expected it to start with `{}`",
+                HEADER,
+            ),
+            ParseCheckpointError::BadLine { line, found } => write!(
+                f,
+                "line {}: expected `size N`, `depth K` or `done ROWS COUNT`, found `{}`",
+                line, found,
+            ),
+            ParseCheckpointError::Missing { field } => write!(
+                f,
+                "the `{}` line is missing",
+                field,
+            ),
+            ParseCheckpointError::UnknownUnit { line, unit } => write!(
+                f,
+                "line {}: `{}` isn't one of the work units",
+                line, unit,
+            ),
+            ParseCheckpointError::DuplicateUnit { line, unit } => write!(
+                f,
+                "line {}: work unit `{}` is already done",
+                line, unit,
+            ),
+        }
+    }
+}
+
+impl Error for ParseCheckpointError {}
+
+impl Checkpoint {
+    /// A count of the `side_size`-queens problem that hasn't started yet, split into a work unit
+    /// for every valid placement of the queens in the first `depth` columns. Deeper units are
+    /// smaller and more numerous, so less is lost when the count is stopped.
+    pub fn new(side_size: usize, depth: usize) -> Checkpoint {
+        let units = with_row_set!(side_size, |S| work_units::<S>(side_size, depth));
+        let done = vec![None; units.len()];
+        Checkpoint { side_size, depth, units, done }
+    }
+
+    pub fn side_size(&self) -> usize {
+        self.side_size
+    }
+
+    /// The number of columns whose queens make up a work unit.
+    pub fn depth(&self) -> usize {
+        self.depth
+    }
+
+    /// The number of work units.
+    pub fn units(&self) -> usize {
+        self.units.len()
+    }
+
+    /// The number of work units that have been counted.
+    pub fn units_done(&self) -> usize {
+        self.done.iter().flatten().count()
+    }
+
+    /// The number of solutions the finished work units stand for.
+    pub fn count_so_far(&self) -> usize {
+        self.done.iter().flatten().sum()
+    }
+
+    /// The number of solutions, or `None` if there are work units left.
+    pub fn count(&self) -> Option<usize> {
+        self.done.iter().copied().sum()
+    }
+
+    /// Counts the work units that are left, in parallel, and returns the number of solutions.
+    /// `save` is called with the checkpoint after every unit finishes, one call at a time. If it
+    /// fails, the count stops as soon as the units under way finish and the error is returned.
+    pub fn run<F, E>(&mut self, save: F) -> Result<usize, E>
+        where F: FnMut(&Checkpoint) -> Result<(), E> + Send,
+              E: Send,
+    {
+        with_row_set!(self.side_size, |S| self.run_with::<S, F, E>(save))
+    }
+
+    fn run_with<S: RowSet, F, E>(&mut self, save: F) -> Result<usize, E>
+        where F: FnMut(&Checkpoint) -> Result<(), E> + Send,
+              E: Send,
+    {
+        let search = BitSearch::<S>::new(self.side_size);
+        let left: Vec<_> = self.units.iter().cloned().enumerate().filter(|&(i, _)| self.done[i].is_none()).collect();
+        let state = Mutex::new((&mut *self, save));
+        left.into_par_iter().try_for_each(|(i, rows)| {
+            let count = search.count_prefix_solutions(&rows, SearchMode::Mirror);
+            let mut state = state.lock().unwrap();
+            let (checkpoint, save) = &mut *state;
+            checkpoint.done[i] = Some(count);
+            save(checkpoint)
+        })?;
+        Ok(self.count_so_far())
+    }
+}
+
+/// The work units of the `side_size`-queens problem split `depth` columns deep, apart from the
+/// ones mirroring leaves out.
+fn work_units<S: RowSet>(side_size: usize, depth: usize) -> Vec<Vec<Option<usize>>> {
+    let search = BitSearch::<S>::new(side_size);
+    search.prefixes(depth).into_iter()
+        .filter(|rows| search.prefix_weight(rows, SearchMode::Mirror) > 0)
+        .collect()
+}
+
+/// Writes a unit's rows separated by commas, or `-` for the single unit of an empty board.
+fn unit_name(rows: &[Option<usize>]) -> String {
+    if rows.is_empty() {
+        return "-".to_string();
+    }
+    let rows: Vec<_> = rows.iter().map(|row| row.expect("work unit with an empty column").to_string()).collect();
+    rows.join(",")
+}
+
+impl fmt::Display for Checkpoint {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", HEADER)?;
+        writeln!(f, "size {}", self.side_size)?;
+        writeln!(f, "depth {}", self.depth)?;
+        for (rows, count) in self.units.iter().zip(&self.done) {
+            if let Some(count) = count {
+                writeln!(f, "done {} {}", unit_name(rows), count)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for Checkpoint {
+    type Err = ParseCheckpointError;
+
+    fn from_str(s: &str) -> Result<Checkpoint, ParseCheckpointError> {
+        let mut lines = s.lines().enumerate().map(|(i, line)| (i + 1, line.trim()));
+        if lines.next().map(|(_, line)| line) != Some(HEADER) {
+            return Err(ParseCheckpointError::BadHeader);
+        }
+
+        let mut side_size = None;
+        let mut depth = None;
+        let mut checkpoint: Option<(Checkpoint, HashMap<String, usize>)> = None;
+        for (line, text) in lines.filter(|(_, text)| !text.is_empty()) {
+            let bad_line = || ParseCheckpointError::BadLine { line, found: text.to_string() };
+            let fields: Vec<_> = text.split_whitespace().collect();
+            match (fields[0], &fields[1..]) {
+                ("size", [size]) if checkpoint.is_none() => side_size = Some(size.parse().map_err(|_| bad_line())?),
+                ("depth", [k]) if checkpoint.is_none() => depth = Some(k.parse().map_err(|_| bad_line())?),
+                ("done", [unit, count]) => {
+                    let count = count.parse().map_err(|_| bad_line())?;
+                    let (checkpoint, units) = match &mut checkpoint {
+                        Some(checkpoint) => checkpoint,
+                        None => checkpoint.insert(new_checkpoint(side_size, depth)?),
+                    };
+                    let i = *units.get(*unit)
+                        .ok_or_else(|| ParseCheckpointError::UnknownUnit { line, unit: unit.to_string() })?;
+                    if checkpoint.done[i].is_some() {
+                        return Err(ParseCheckpointError::DuplicateUnit { line, unit: unit.to_string() });
+                    }
+                    checkpoint.done[i] = Some(count);
+                }
+                _ => return Err(bad_line()),
+            }
+        }
+        match checkpoint {
+            Some((checkpoint, _)) => Ok(checkpoint),
+            None => new_checkpoint(side_size, depth).map(|(checkpoint, _)| checkpoint),
+        }
+    }
+}
+
+/// A fresh checkpoint for the `size` and `depth` lines read so far, and the index of each of its
+/// work units by name.
+fn new_checkpoint(
+    side_size: Option<usize>,
+    depth: Option<usize>,
+) -> Result<(Checkpoint, HashMap<String, usize>), ParseCheckpointError> {
+    let side_size = side_size.ok_or(ParseCheckpointError::Missing { field: "size" })?;
+    let depth = depth.ok_or(ParseCheckpointError::Missing { field: "depth" })?;
+    let checkpoint = Checkpoint::new(side_size, depth);
+    let units = checkpoint.units.iter().enumerate().map(|(i, rows)| (unit_name(rows), i)).collect();
+    Ok((checkpoint, units))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run() {
+        for side_size in 0..=11 {
+            for depth in 0..=3 {
+                let mut checkpoint = Checkpoint::new(side_size, depth);
+                let mut saves = 0;
+                let count = checkpoint.run(|_| {
+                    saves += 1;
+                    Ok::<(), ()>(())
+                });
+                assert_eq!(count, Ok(crate::count_solutions(side_size)), "side size {}, depth {}", side_size, depth);
+                assert_eq!(saves, checkpoint.units());
+                assert_eq!(checkpoint.count(), count.ok());
+                assert_eq!(checkpoint.units_done(), checkpoint.units());
+            }
+        }
+        // Mirroring leaves out the bottom half of the first column.
+        assert_eq!(Checkpoint::new(8, 1).units(), 4);
+        assert_eq!(Checkpoint::new(9, 1).units(), 5);
+    }
+
+    #[test]
+    fn test_stop_and_resume() {
+        let mut checkpoint = Checkpoint::new(9, 2);
+        let mut last = None;
+        // Units already under way still finish after the error, but they don't get saved.
+        let stopped = checkpoint.run(|checkpoint| {
+            if checkpoint.units_done() > 5 {
+                return Err("stopped");
+            }
+            last = Some(checkpoint.to_string());
+            if checkpoint.units_done() == 5 { Err("stopped") } else { Ok(()) }
+        });
+        assert_eq!(stopped, Err("stopped"));
+        let mut resumed: Checkpoint = last.unwrap().parse().unwrap();
+        assert_eq!((resumed.side_size(), resumed.depth(), resumed.units_done()), (9, 2, 5));
+        assert!(resumed.count_so_far() < 352 && resumed.count().is_none());
+
+        let mut saves = 0;
+        assert_eq!(resumed.run(|_| { saves += 1; Ok::<(), ()>(()) }), Ok(352));
+        assert_eq!(saves, resumed.units() - 5);
+        assert_eq!(resumed.to_string().parse(), Ok(resumed));
+    }
+
+    #[test]
+    fn test_parse() {
+        let checkpoint: Checkpoint = "nqueens checkpoint 1\nsize 8\ndepth 2\ndone 0,2 4\n\ndone 0,3 16\n".parse().unwrap();
+        assert_eq!((checkpoint.units_done(), checkpoint.count_so_far()), (2, 20));
+        let empty: Checkpoint = "nqueens checkpoint 1\nsize 0\ndepth 2\ndone - 1\n".parse().unwrap();
+        assert_eq!(empty.count(), Some(1));
+        assert_eq!(empty.to_string(), "nqueens checkpoint 1\nsize 0\ndepth 2\ndone - 1\n");
+
+        assert_eq!("size 8\n".parse::<Checkpoint>(), Err(ParseCheckpointError::BadHeader));
+        assert_eq!(
+            "nqueens checkpoint 1\nsize eight\n".parse::<Checkpoint>(),
+            Err(ParseCheckpointError::BadLine { line: 2, found: "size eight".to_string() }),
+        );
+        assert_eq!(
+            "nqueens checkpoint 1\nsize 8\ndone 0,2 4\n".parse::<Checkpoint>(),
+            Err(ParseCheckpointError::Missing { field: "depth" }),
+        );
+        // Rows 0 and 1 attack each other, and the first queen on row 7 is mirrored away.
+        for unit in ["0,1", "7,0", "0"] {
+            assert_eq!(
+                format!("nqueens checkpoint 1\nsize 8\ndepth 2\ndone {} 4\n", unit).parse::<Checkpoint>(),
+                Err(ParseCheckpointError::UnknownUnit { line: 4, unit: unit.to_string() }),
+            );
+        }
+        assert!("nqueens checkpoint 1\nsize 8\ndepth 2\ndone 0,2 4\nsize 9\n".parse::<Checkpoint>().is_err());
+        for count in [4, 5] {
+            assert_eq!(
+                format!("nqueens checkpoint 1\nsize 8\ndepth 2\ndone 0,2 4\ndone 0,3 16\ndone 0,2 {}\n", count).parse::<Checkpoint>(),
+                Err(ParseCheckpointError::DuplicateUnit { line: 6, unit: "0,2".to_string() }),
+            );
+        }
+    }
+}
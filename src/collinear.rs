@@ -0,0 +1,212 @@
+//! The no-three-in-line variant: solutions of the n-queens problem where no three queens lie on a
+//! common straight line of any slope, not just the rows, columns and diagonals queens attack
+//! along.
+//!
+//! The search is the usual column-by-column one, keeping for each later column the rows a queen
+//! there can't take. Besides the rows the queens attack, each new queen rules out, for every
+//! queen to its left, the squares further along the line through the two of them, so the
+//! branches that would put a third queen on it are never explored.
+
+use crate::bitboard::{with_row_set, RowSet};
+use crate::board::{Board, Queen};
+use rayon::prelude::*;
+use std::ops::ControlFlow;
+
+impl Board {
+    /// Whether three of the board's queens lie on a common straight line, of any slope.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// // The queens of columns 1, 3 and 5 lie on a line two columns across for every row down.
+    /// assert!(Board::from_rows(&[0, 4, 7, 5, 2, 6, 1, 3]).unwrap().has_three_in_line());
+    /// assert!(!Board::from_rows(&[1, 3, 0, 2]).unwrap().has_three_in_line());
+    /// ```
+    pub fn has_three_in_line(&self) -> bool {
+        let queens = self.queens();
+        (0..queens.len()).any(|i| {
+            (i + 1..queens.len()).any(|j| queens[j + 1..].iter().any(|&c| are_collinear(queens[i], queens[j], c)))
+        })
+    }
+}
+
+/// Whether `a`, `b` and `c` lie on a common straight line.
+fn are_collinear(a: Queen, b: Queen, c: Queen) -> bool {
+    let (ax, ay) = (a.x as isize, a.y as isize);
+    let (bx, by) = (b.x as isize, b.y as isize);
+    let (cx, cy) = (c.x as isize, c.y as isize);
+    (bx - ax) * (cy - ay) == (by - ay) * (cx - ax)
+}
+
+/// The number of solutions of the `side_size`-queens problem with no three queens on a common
+/// straight line.
+///
+/// ```
+/// assert_eq!(nqueens::count_no_three_in_line_solutions(7), 0);
+/// assert_eq!(nqueens::count_no_three_in_line_solutions(8), 8);
+/// ```
+pub fn count_no_three_in_line_solutions(side_size: usize) -> usize {
+    with_row_set!(side_size, |S| CollinearSearch::<S>::new(side_size).count())
+}
+
+/// Calls `f` with every solution of the `side_size`-queens problem with no three queens on a
+/// common straight line, in row order.
+pub fn for_each_no_three_in_line_solution<F>(side_size: usize, mut f: F)
+    where F: FnMut(&Board)
+{
+    let _ = try_for_each::<()>(side_size, &mut |board| {
+        f(board);
+        ControlFlow::Continue(())
+    });
+}
+
+/// The first solution of the `side_size`-queens problem with no three queens on a common
+/// straight line in row order, or `None` if there isn't one.
+pub fn first_no_three_in_line_solution(side_size: usize) -> Option<Board> {
+    match try_for_each(side_size, &mut |board| ControlFlow::Break(board.clone())) {
+        ControlFlow::Break(board) => Some(board),
+        ControlFlow::Continue(()) => None,
+    }
+}
+
+fn try_for_each<B>(side_size: usize, f: &mut dyn FnMut(&Board) -> ControlFlow<B>) -> ControlFlow<B> {
+    with_row_set!(side_size, |S| CollinearSearch::<S>::new(side_size).try_for_each(f))
+}
+
+/// A search through the columns of a `side_size`×`side_size` board from left to right.
+struct CollinearSearch<S> {
+    side_size: usize,
+    full: S,
+}
+
+impl<S: RowSet> CollinearSearch<S> {
+    fn new(side_size: usize) -> CollinearSearch<S> {
+        CollinearSearch { side_size, full: S::full(side_size) }
+    }
+
+    /// For each column, the rows no queen may take, before any queens are placed.
+    fn start(&self) -> Vec<S> {
+        vec![S::empty(self.side_size); self.side_size]
+    }
+
+    /// Puts the queen of column `rows.len()` on `row`, ruling out the squares it attacks in later
+    /// columns and the ones in line with it and an earlier queen.
+    fn place(&self, rows: &[usize], row: usize, blocked: &[S]) -> Vec<S> {
+        let n = self.side_size as isize;
+        let col = rows.len();
+        let mut blocked = blocked.to_vec();
+        let mut block = |x: usize, y: isize| {
+            if (0..n).contains(&y) {
+                blocked[x].insert(y as usize);
+            }
+        };
+        for x in col + 1..self.side_size {
+            let distance = (x - col) as isize;
+            block(x, row as isize);
+            block(x, row as isize + distance);
+            block(x, row as isize - distance);
+        }
+        for (prev_col, &prev_row) in rows.iter().enumerate() {
+            let (dx, dy) = ((col - prev_col) as isize, row as isize - prev_row as isize);
+            for x in col + 1..self.side_size {
+                let steps = dy * (x - prev_col) as isize;
+                if steps % dx == 0 {
+                    block(x, prev_row as isize + steps / dx);
+                }
+            }
+        }
+        blocked
+    }
+
+    /// Counts the solutions, searching each row of the first column in parallel.
+    fn count(&self) -> usize {
+        if self.side_size == 0 {
+            return 1;
+        }
+        let start = self.start();
+        (0..self.side_size).into_par_iter()
+            .map(|row| self.count_from(&mut vec![row], &self.place(&[], row, &start)))
+            .sum()
+    }
+
+    fn count_from(&self, rows: &mut Vec<usize>, blocked: &[S]) -> usize {
+        let col = rows.len();
+        if col == self.side_size {
+            return 1;
+        }
+        let mut count = 0;
+        let mut free = self.full.difference(&blocked[col]);
+        while let Some(row) = free.pop_first() {
+            let next = self.place(rows, row, blocked);
+            rows.push(row);
+            count += self.count_from(rows, &next);
+            rows.pop();
+        }
+        count
+    }
+
+    fn try_for_each<B>(&self, f: &mut dyn FnMut(&Board) -> ControlFlow<B>) -> ControlFlow<B> {
+        self.try_for_each_from(&mut vec![], &self.start(), f)
+    }
+
+    fn try_for_each_from<B>(
+        &self,
+        rows: &mut Vec<usize>,
+        blocked: &[S],
+        f: &mut dyn FnMut(&Board) -> ControlFlow<B>,
+    ) -> ControlFlow<B> {
+        let col = rows.len();
+        if col == self.side_size {
+            return f(&Board::from_rows(rows).expect("search placed a queen off the board"));
+        }
+        let mut free = self.full.difference(&blocked[col]);
+        while let Some(row) = free.pop_first() {
+            let next = self.place(rows, row, blocked);
+            rows.push(row);
+            self.try_for_each_from(rows, &next, f)?;
+            rows.pop();
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The number of solutions with no three queens in line, from n = 1.
+    const NO_THREE_IN_LINE_COUNTS: [usize; 14] = [1, 0, 0, 2, 0, 0, 0, 8, 32, 40, 96, 410, 1392, 4416];
+
+    #[test]
+    fn test_count_no_three_in_line_solutions() {
+        for (side_size, &expected) in (1..).zip(&NO_THREE_IN_LINE_COUNTS) {
+            assert_eq!(count_no_three_in_line_solutions(side_size), expected, "side size {}", side_size);
+        }
+    }
+
+    #[test]
+    fn test_no_three_in_line() {
+        for side_size in 0..=10 {
+            let expected: Vec<_> = Board::solutions(side_size).filter(|b| !b.has_three_in_line()).collect();
+            assert_eq!(count_no_three_in_line_solutions(side_size), expected.len(), "side size {}", side_size);
+
+            let mut solutions = vec![];
+            for_each_no_three_in_line_solution(side_size, |board| solutions.push(board.clone()));
+            assert_eq!(solutions, expected, "side size {}", side_size);
+            assert_eq!(first_no_three_in_line_solution(side_size).as_ref(), expected.first(), "side size {}", side_size);
+        }
+    }
+
+    #[test]
+    fn test_has_three_in_line() {
+        // A line of slope 2 through (0, 0), (1, 2) and (2, 4).
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(1, 2), Queen::new(2, 4)]).unwrap();
+        assert!(board.has_three_in_line());
+        // Slope 1/2: the line misses the middle column's squares.
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(2, 1), Queen::new(3, 3)]).unwrap();
+        assert!(!board.has_three_in_line());
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(2, 1), Queen::new(4, 2)]).unwrap();
+        assert!(board.has_three_in_line());
+        assert!(!Board::new(3).has_three_in_line());
+    }
+}
@@ -0,0 +1,220 @@
+//! Completing a board that already holds some queens: finding every way to fill the remaining
+//! columns so that the whole board is a solution. The queens already placed can be in any
+//! columns, unlike with [`find_valid_boards`](crate::find_valid_boards), which only fills the
+//! columns to the right of a given one. Blocked squares are left empty, so completing an empty
+//! board with holes in it solves the n-queens problem on that board.
+//!
+//! Deciding whether a partial placement can be completed at all is NP-complete (Gent, Jefferson
+//! and Nightingale, 2017), so this is still a full backtracking search. Each fixed queen rules out
+//! some rows of every other column up front, which prunes the search early.
+
+use crate::bitboard::Constraints;
+use crate::board::{Board, Queen};
+use crate::search::{count_constrained_solutions, par_constrained_solutions, Shape, Solutions};
+use rayon::prelude::*;
+use std::error::Error;
+use std::fmt;
+
+/// Returned when the queens already on a board can't be part of any solution.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CompletionError {
+    /// Two of the queens already on the board attack each other.
+    Attacking(Queen, Queen),
+    /// A queen already on the board stands on a blocked square.
+    Blocked(Queen),
+    /// The board has pawns on it. Their rows and columns can hold more than one queen, which the
+    /// column-by-column search can't express.
+    Pawns,
+}
+
+impl fmt::Display for CompletionError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            CompletionError::Attacking(a, b) => write!(
+                f,
+                "the queens at column {}, row {} and column {}, row {} attack each other",
+                a.x, a.y, b.x, b.y,
+            ),
+            CompletionError::Blocked(queen) => write!(
+                f,
+                "the queen at column {}, row {} stands on a blocked square",
+                queen.x, queen.y,
+            ),
+            CompletionError::Pawns => write!(f, "boards with pawns can't be completed"),
+        }
+    }
+}
+
+impl Error for CompletionError {}
+
+impl Board {
+    /// Lazily yields every solution that keeps all of this board's queens and leaves its blocked
+    /// squares empty, in row order.
+    ///
+    /// ```
+    /// use nqueens::{Board, Queen};
+    ///
+    /// let board = Board::with_queens(8, vec![Queen::new(3, 0), Queen::new(6, 7)]).unwrap();
+    /// for solution in board.completions().unwrap() {
+    ///     assert!(solution.is_complete() && solution.is_valid());
+    ///     assert!(solution.queens().contains(&Queen::new(3, 0)));
+    /// }
+    /// ```
+    pub fn completions(&self) -> Result<Solutions, CompletionError> {
+        let constraints = self.completion_constraints()?;
+        Ok(Solutions::constrained(self.completion_shape(), &constraints, vec![]))
+    }
+
+    /// Parallel version of [`completions`](Board::completions). Yields every completion exactly
+    /// once, in no particular order.
+    pub fn par_completions(&self) -> Result<impl ParallelIterator<Item=Board>, CompletionError> {
+        let constraints = self.completion_constraints()?;
+        Ok(par_constrained_solutions(self.completion_shape(), constraints))
+    }
+
+    /// The number of solutions that keep all of this board's queens and leave its blocked squares
+    /// empty.
+    ///
+    /// ```
+    /// use nqueens::{Board, Square};
+    ///
+    /// let holed = Board::new(8).with_blocked_squares(vec![Square::new(3, 3), Square::new(4, 4)]).unwrap();
+    /// assert_eq!(holed.count_completions(), Ok(76));
+    /// ```
+    pub fn count_completions(&self) -> Result<usize, CompletionError> {
+        let constraints = self.completion_constraints()?;
+        Ok(count_constrained_solutions(self.completion_shape(), &constraints))
+    }
+
+    /// The board's shape, filled up to one queen per column or row, whichever there are fewer of.
+    fn completion_shape(&self) -> Shape {
+        Shape {
+            width: self.width(),
+            height: self.height(),
+            queens: self.width().min(self.height()),
+        }
+    }
+
+    fn completion_constraints(&self) -> Result<Constraints, CompletionError> {
+        if !self.pawns().is_empty() {
+            return Err(CompletionError::Pawns);
+        }
+        if let Some(&queen) = self.queens().iter().find(|&&q| self.is_blocked(q)) {
+            return Err(CompletionError::Blocked(queen));
+        }
+        if let Some((a, b)) = self.first_attacking_pair() {
+            return Err(CompletionError::Attacking(a, b));
+        }
+        Ok(Constraints {
+            fixed: self.queens().to_vec(),
+            blocked: self.blocked_squares().to_vec(),
+            geometry: self.geometry(),
+        })
+    }
+
+    /// Two queens that attack each other, found in a single pass by remembering the first queen
+    /// seen on every line.
+    fn first_attacking_pair(&self) -> Option<(Queen, Queen)> {
+        let (width, height) = (self.width(), self.height());
+        let diagonals = (width + height).saturating_sub(1);
+        let mut cols = vec![None; width];
+        let mut rows = vec![None; height];
+        let mut sw_diagonals = vec![None; diagonals];
+        let mut se_diagonals = vec![None; diagonals];
+        for &queen in self.queens() {
+            let (sw, se) = self.diagonals(queen);
+            for seen in [
+                &mut cols[queen.col()],
+                &mut rows[queen.row()],
+                &mut sw_diagonals[sw],
+                &mut se_diagonals[se],
+            ] {
+                if let Some(other) = *seen {
+                    return Some((other, queen));
+                }
+                *seen = Some(queen);
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Square;
+
+    fn completions_by_filtering(board: &Board) -> Vec<Board> {
+        let queens = board.width().min(board.height());
+        let mut completions: Vec<_> = Board::placements(board.width(), board.height(), queens)
+            .filter(|solution| board.queens().iter().all(|q| solution.queens().contains(q)))
+            .filter(|solution| solution.queens().iter().all(|&q| !board.is_blocked(q)))
+            .map(|solution| solution.with_blocked_squares(board.blocked_squares().iter().copied()).unwrap())
+            .collect();
+        completions.sort_by(|a, b| a.queens().cmp(b.queens()));
+        completions
+    }
+
+    #[test]
+    fn test_completions() {
+        let boards = [
+            Board::new(8),
+            Board::with_queens(8, vec![Queen::new(5, 2)]).unwrap(),
+            Board::with_queens(8, vec![Queen::new(7, 3), Queen::new(2, 0)]).unwrap(),
+            Board::with_queens(9, vec![Queen::new(4, 4)]).unwrap(),
+            Board::with_queens(9, vec![Queen::new(1, 7), Queen::new(6, 1), Queen::new(8, 5)]).unwrap(),
+            Board::from_rows(&[0, 4, 7, 5, 2, 6, 1, 3]).unwrap(),
+            Board::new(8).with_blocked_squares(vec![Square::new(0, 0), Square::new(3, 5)]).unwrap(),
+            Board::rect(9, 6).with_added_queens(vec![Queen::new(7, 2)]).unwrap(),
+            Board::rect(5, 8).with_added_queens(vec![Queen::new(2, 6)]).unwrap()
+                .with_blocked_squares(vec![Square::new(0, 0)])
+                .unwrap(),
+            Board::with_queens(9, vec![Queen::new(2, 2)]).unwrap()
+                .with_blocked_squares((0..9).map(|x| Square::new(x, 6)).filter(|s| s.x != 4))
+                .unwrap(),
+        ];
+        for board in &boards {
+            let expected = completions_by_filtering(board);
+            let mut completions: Vec<_> = board.completions().unwrap().collect();
+            completions.sort_by(|a, b| a.queens().cmp(b.queens()));
+            assert_eq!(completions, expected, "{:?}", board);
+            assert_eq!(board.count_completions().unwrap(), expected.len(), "{:?}", board);
+
+            let mut par_completions: Vec<_> = board.par_completions().unwrap().collect();
+            par_completions.sort_by(|a, b| a.queens().cmp(b.queens()));
+            assert_eq!(par_completions, expected, "{:?}", board);
+        }
+
+        // A queen in the corner of a 4×4 board rules out both solutions.
+        let corner = Board::with_queens(4, vec![Queen::new(0, 0)]).unwrap();
+        assert_eq!(corner.count_completions(), Ok(0));
+        assert_eq!(corner.completions().unwrap().next(), None);
+
+        // Blocking a whole column leaves nothing to complete.
+        let wall = Board::new(6).with_blocked_squares((0..6).map(|y| Square::new(2, y))).unwrap();
+        assert_eq!(wall.count_completions(), Ok(0));
+    }
+
+    #[test]
+    fn test_invalid_completions() {
+        let cases = [
+            (vec![Queen::new(0, 0), Queen::new(5, 0)], (Queen::new(0, 0), Queen::new(5, 0))),
+            (vec![Queen::new(3, 1), Queen::new(3, 6)], (Queen::new(3, 1), Queen::new(3, 6))),
+            (vec![Queen::new(1, 1), Queen::new(4, 4)], (Queen::new(1, 1), Queen::new(4, 4))),
+            (vec![Queen::new(2, 5), Queen::new(6, 1), Queen::new(0, 2)], (Queen::new(2, 5), Queen::new(6, 1))),
+        ];
+        for (queens, (a, b)) in cases {
+            let board = Board::with_queens(8, queens).unwrap();
+            assert_eq!(board.count_completions(), Err(CompletionError::Attacking(a, b)));
+            assert!(board.completions().is_err());
+        }
+
+        let board = Board::with_queens(8, vec![Queen::new(1, 1), Queen::new(4, 4)]).unwrap()
+            .with_blocked_squares(vec![Square::new(4, 4)])
+            .unwrap();
+        assert_eq!(board.count_completions(), Err(CompletionError::Blocked(Queen::new(4, 4))));
+
+        let board = Board::new(8).with_pawns(vec![Square::new(2, 2)]).unwrap();
+        assert_eq!(board.count_completions(), Err(CompletionError::Pawns));
+    }
+}
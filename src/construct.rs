@@ -0,0 +1,76 @@
+//! Explicit solutions of the n-queens problem, built in linear time without any search.
+//!
+//! This is the construction of Hoffman, Loessi and Moore (1969). Listing the rows of the queens
+//! column by column, put the even-numbered rows first and the odd-numbered rows after them,
+//! counting rows from 1. That's already a solution unless `n mod 6` is 2 or 3, where two
+//! diagonals would clash and a few rows have to be moved:
+//!
+//! * `n mod 6 == 2`: swap rows 1 and 3 and move row 5 to the end, giving `2, 4, …, n, 3, 1, 7, 9,
+//!   …, n - 1, 5`.
+//! * `n mod 6 == 3`: move row 2 to the end of the even rows and rows 1 and 3 to the end of the odd
+//!   rows, giving `4, 6, …, n - 1, 2, 5, 7, …, n, 1, 3`.
+
+use crate::board::Board;
+
+impl Board {
+    /// A solution of the `side_size`-queens problem, built in `O(side_size)` time. Returns `None`
+    /// for 2 and 3, the only sizes without a solution.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let board = Board::construct_solution(1_000_000).unwrap();
+    /// assert!(board.is_complete() && board.is_valid());
+    /// ```
+    pub fn construct_solution(side_size: usize) -> Option<Board> {
+        if side_size == 2 || side_size == 3 {
+            return None;
+        }
+
+        let n = side_size;
+        let evens = (2..=n).step_by(2);
+        let odds = (1..=n).step_by(2);
+        // The rows counted from 1, as in the usual statement of the construction.
+        let rows: Vec<usize> = match n % 6 {
+            2 => evens
+                .chain([3, 1])
+                .chain((7..=n).step_by(2))
+                .chain([5])
+                .collect(),
+            3 => (4..=n).step_by(2)
+                .chain([2])
+                .chain((5..=n).step_by(2))
+                .chain([1, 3])
+                .collect(),
+            _ => evens.chain(odds).collect(),
+        };
+
+        let rows: Vec<usize> = rows.into_iter().map(|row| row - 1).collect();
+        Board::from_rows(&rows)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_construct_solution() {
+        for side_size in 0..=300 {
+            match Board::construct_solution(side_size) {
+                Some(board) => {
+                    assert_eq!(board.side_size(), side_size);
+                    assert!(board.is_complete(), "side size {}", side_size);
+                    assert!(board.is_valid(), "side size {}", side_size);
+                }
+                None => assert!(side_size == 2 || side_size == 3),
+            }
+        }
+    }
+
+    #[test]
+    fn test_construct_huge_solution() {
+        let board = Board::construct_solution(1_000_003).unwrap();
+        assert!(board.is_complete() && board.is_valid());
+    }
+}
@@ -0,0 +1,309 @@
+//! Queens in three dimensions: an `n`×`n`×`n` cube, made of `n` layers stacked on top of each
+//! other, where a queen attacks along any line through it that's straight in every coordinate.
+//! There are 13 of those lines: the 3 axes, the 6 diagonals of the planes through the queen and
+//! the 4 diagonals through the corners of the cube.
+//!
+//! The cube has `n²` lines along its rows and each holds at most one queen, but far fewer queens
+//! than that fit once the other lines come into play, and how many is only known from searching:
+//! 1, 1, 4, 7 and 13 for the first five cubes (OEIS A068940).
+//!
+//! The search goes through the rows of the cube one by one, trying each free cell of a row for a
+//! queen before leaving it empty, and gives up on a branch as soon as the rows left that still
+//! have a free cell can't hold the queens it's looking for. It asks for one more queen each time
+//! until none fit.
+
+use crate::bitboard::{with_row_set, RowSet};
+use crate::board::{Board, Queen};
+use rayon::prelude::*;
+
+/// A queen's position in a cube. `x` is the column, `y` the row and `z` the layer, all counted
+/// from the top-left corner of the first layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Queen3 {
+    pub x: usize,
+    pub y: usize,
+    pub z: usize,
+}
+
+/// A cell of the cube, addressed the same way as a queen standing on it.
+pub type Cell = Queen3;
+
+/// An `n`×`n`×`n` cube holding any number of queens.
+///
+/// Queens are kept sorted by layer, then by row and then by column, so two cubes with the same
+/// placement always compare equal.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Cube {
+    queens: Vec<Queen3>,
+    side_size: usize,
+}
+
+/// The most queens that fit in a cube, found by [`Cube::max_queens`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MaxCubeQueens {
+    pub queens: usize,
+    /// A cube with that many queens in it that don't attack each other.
+    pub cube: Cube,
+}
+
+impl Queen3 {
+    /// One step along each of the lines a queen attacks on, pointing away from the first layer
+    /// or, within a layer, away from the top-left corner.
+    pub const DIRECTIONS: [(isize, isize, isize); 13] = [
+        (1, 0, 0), (0, 1, 0), (0, 0, 1),
+        (1, 1, 0), (1, -1, 0), (1, 0, 1), (-1, 0, 1), (0, 1, 1), (0, -1, 1),
+        (1, 1, 1), (1, -1, 1), (-1, 1, 1), (-1, -1, 1),
+    ];
+
+    pub fn new(x: usize, y: usize, z: usize) -> Queen3 {
+        Queen3 { x, y, z }
+    }
+
+    /// Whether a queen here attacks `other`, which it does if they lie on a common line along
+    /// one of the [`DIRECTIONS`](Queen3::DIRECTIONS).
+    ///
+    /// ```
+    /// use nqueens::Queen3;
+    ///
+    /// let queen = Queen3::new(0, 0, 0);
+    /// assert!(queen.attacks(Queen3::new(3, 3, 3)));
+    /// assert!(queen.attacks(Queen3::new(0, 2, 2)));
+    /// assert!(!queen.attacks(Queen3::new(1, 2, 2)));
+    /// ```
+    pub fn attacks(&self, other: Queen3) -> bool {
+        let deltas = [
+            other.x as isize - self.x as isize,
+            other.y as isize - self.y as isize,
+            other.z as isize - self.z as isize,
+        ];
+        let distance = deltas.iter().map(|d| d.abs()).max().unwrap_or(0);
+        distance > 0 && deltas.iter().all(|&d| d == 0 || d.abs() == distance)
+    }
+}
+
+impl Cube {
+    /// Creates an empty `side_size`×`side_size`×`side_size` cube.
+    pub fn new(side_size: usize) -> Cube {
+        Cube { queens: vec![], side_size }
+    }
+
+    /// Creates a `side_size`×`side_size`×`side_size` cube with the given queens already placed,
+    /// or `None` if any queen lies outside the cube or two queens share a cell. The placement
+    /// doesn't have to be valid.
+    pub fn with_queens(side_size: usize, queens: impl IntoIterator<Item=Queen3>) -> Option<Cube> {
+        let mut cube = Cube::new(side_size);
+        cube.queens.extend(queens);
+        cube.queens.sort_by_key(|q| (q.z, q.y, q.x));
+        let in_bounds = cube.queens.iter().all(|&q| cube.contains(q));
+        let distinct = cube.queens.windows(2).all(|w| w[0] != w[1]);
+        if in_bounds && distinct {
+            Some(cube)
+        } else {
+            None
+        }
+    }
+
+    /// The length of the cube's edges.
+    pub fn side_size(&self) -> usize {
+        self.side_size
+    }
+
+    /// Whether `cell` lies inside the cube.
+    fn contains(&self, cell: Cell) -> bool {
+        cell.x < self.side_size && cell.y < self.side_size && cell.z < self.side_size
+    }
+
+    /// The queens in the cube, sorted by layer, then by row and then by column.
+    pub fn queens(&self) -> &[Queen3] {
+        &self.queens
+    }
+
+    /// Layer `z` of the cube as a flat board, with the queens standing in it.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `z` lies outside the cube.
+    pub fn layer(&self, z: usize) -> Board {
+        assert!(z < self.side_size, "layer {} lies outside a cube of side size {}", z, self.side_size);
+        let queens = self.queens.iter().filter(|q| q.z == z).map(|q| Queen::new(q.x, q.y));
+        Board::with_queens(self.side_size, queens).expect("queens of a layer lie on its board")
+    }
+
+    /// Renders the cube one layer after the other, each the way
+    /// [`Board::get_board_string`] draws it and followed by an empty line.
+    pub fn get_cube_string(&self) -> String {
+        (0..self.side_size).map(|z| self.layer(z).get_board_string() + "\n").collect()
+    }
+
+    /// Returns a copy of the cube with `queen` added, or `None` if the cell is taken or the new
+    /// queen would attack another one.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `queen` lies outside the cube.
+    pub fn try_insert_queen(&self, queen: Queen3) -> Option<Cube> {
+        assert!(self.contains(queen));
+        if self.queens.iter().any(|&q| q == queen || q.attacks(queen)) {
+            return None;
+        }
+        let mut new_cube = self.clone();
+        new_cube.queens.push(queen);
+        new_cube.queens.sort_by_key(|q| (q.z, q.y, q.x));
+        Some(new_cube)
+    }
+
+    /// Whether no two queens in the cube attack each other.
+    pub fn is_valid(&self) -> bool {
+        self.queens.iter().enumerate().all(|(i, a)| self.queens[i + 1..].iter().all(|&b| !a.attacks(b)))
+    }
+
+    /// The most queens that fit in a `side_size`×`side_size`×`side_size` cube without any two
+    /// attacking each other, along with a cube showing how. Practical up to about 5×5×5.
+    ///
+    /// ```
+    /// use nqueens::Cube;
+    ///
+    /// let max = Cube::max_queens(4);
+    /// assert_eq!(max.queens, 7);
+    /// assert!(max.cube.is_valid());
+    /// ```
+    pub fn max_queens(side_size: usize) -> MaxCubeQueens {
+        let queens = with_row_set!(side_size.pow(3), |S| CubeSearch::<S>::new(side_size).maximum());
+        let cube = Cube::with_queens(side_size, queens).expect("cube search placed a queen outside the cube");
+        MaxCubeQueens { queens: cube.queens().len(), cube }
+    }
+}
+
+/// A search through the rows of a cube, numbered layer by layer. Cells are numbered the same
+/// way, so row `r` holds cells `r * n..(r + 1) * n`.
+struct CubeSearch<S> {
+    side_size: usize,
+    /// For each cell, the cells a queen on it attacks, itself included.
+    attacks: Vec<S>,
+}
+
+impl<S: RowSet> CubeSearch<S> {
+    fn new(side_size: usize) -> CubeSearch<S> {
+        let n = side_size;
+        let cells = n * n * n;
+        let cell = |i: usize| Cell::new(i % n, i / n % n, i / (n * n));
+        let attacks = (0..cells)
+            .map(|i| {
+                let mut attacked = S::empty(cells);
+                for j in (0..cells).filter(|&j| i == j || cell(i).attacks(cell(j))) {
+                    attacked.insert(j);
+                }
+                attacked
+            })
+            .collect();
+        CubeSearch { side_size, attacks }
+    }
+
+    fn cell(&self, i: usize) -> Cell {
+        let n = self.side_size;
+        Cell::new(i % n, i / n % n, i / (n * n))
+    }
+
+    /// The cells of a placement with as many queens as possible.
+    fn maximum(&self) -> Vec<Queen3> {
+        let all = S::full(self.attacks.len());
+        let mut best = vec![];
+        for queens in 1..=self.side_size * self.side_size {
+            match self.place(&all, queens) {
+                Some(cells) => best = cells,
+                None => break,
+            }
+        }
+        best.into_iter().map(|i| self.cell(i)).collect()
+    }
+
+    /// Places `queens` queens on the `free` cells, or returns `None` if they don't fit. Each way
+    /// to fill or skip the first row is tried in parallel.
+    fn place(&self, free: &S, queens: usize) -> Option<Vec<usize>> {
+        let n = self.side_size;
+        (0..=n).into_par_iter().find_map_any(|x| {
+            if x == n {
+                return self.place_from(1, free, queens);
+            }
+            let mut cells = self.place_from(1, &free.difference(&self.attacks[x]), queens - 1)?;
+            cells.push(x);
+            Some(cells)
+        })
+    }
+
+    /// Places `queens_left` queens on the `free` cells of rows `row` onwards.
+    fn place_from(&self, row: usize, free: &S, queens_left: usize) -> Option<Vec<usize>> {
+        if queens_left == 0 {
+            return Some(vec![]);
+        }
+        let n = self.side_size;
+        let rows_with_room = (row..n * n)
+            .filter(|&r| (r * n..(r + 1) * n).any(|i| free.contains(i)))
+            .count();
+        if rows_with_room < queens_left {
+            return None;
+        }
+
+        (row * n..(row + 1) * n)
+            .filter(|&i| free.contains(i))
+            .find_map(|i| {
+                let mut cells = self.place_from(row + 1, &free.difference(&self.attacks[i]), queens_left - 1)?;
+                cells.push(i);
+                Some(cells)
+            })
+            .or_else(|| self.place_from(row + 1, free, queens_left))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_max_queens() {
+        // OEIS A068940, from n = 1.
+        for (side_size, &expected) in (1..).zip(&[1, 1, 4, 7, 13]) {
+            let max = Cube::max_queens(side_size);
+            assert_eq!(max.queens, expected, "side size {}", side_size);
+            assert!(max.cube.is_valid(), "side size {}", side_size);
+            for z in 0..side_size {
+                assert!(max.cube.layer(z).is_valid(), "side size {}", side_size);
+            }
+        }
+        assert_eq!(Cube::max_queens(0), MaxCubeQueens { queens: 0, cube: Cube::new(0) });
+    }
+
+    #[test]
+    fn test_attacks() {
+        let center = Queen3::new(2, 2, 2);
+        for &(dx, dy, dz) in &Queen3::DIRECTIONS {
+            for steps in [-2, -1, 1, 2] {
+                let cell = |d: isize| (2 + d * steps) as usize;
+                let other = Queen3::new(cell(dx), cell(dy), cell(dz));
+                assert!(center.attacks(other) && other.attacks(center));
+            }
+        }
+        let attacked = (0..125)
+            .map(|i| Queen3::new(i % 5, i / 5 % 5, i / 25))
+            .filter(|&cell| center.attacks(cell))
+            .count();
+        assert_eq!(attacked, 13 * 4);
+        assert!(!center.attacks(center));
+        assert!(!center.attacks(Queen3::new(3, 4, 2)));
+    }
+
+    #[test]
+    fn test_try_insert_queen() {
+        let cube = Cube::new(3).try_insert_queen(Queen3::new(0, 0, 0)).unwrap();
+        assert!(cube.try_insert_queen(Queen3::new(2, 2, 2)).is_none());
+        assert!(cube.try_insert_queen(Queen3::new(0, 0, 0)).is_none());
+        let cube = cube.try_insert_queen(Queen3::new(1, 2, 0)).unwrap();
+        assert_eq!(cube.queens(), [Queen3::new(0, 0, 0), Queen3::new(1, 2, 0)]);
+        assert!(cube.is_valid());
+        assert_eq!(cube.get_cube_string(), "QQ____\n______\n__QQ__\n\n______\n______\n______\n\n______\n______\n______\n\n");
+
+        assert!(Cube::with_queens(3, vec![Queen3::new(0, 0, 3)]).is_none());
+        let cube = Cube::with_queens(3, vec![Queen3::new(0, 0, 0), Queen3::new(1, 0, 1)]).unwrap();
+        assert!(!cube.is_valid());
+    }
+}
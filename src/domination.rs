@@ -0,0 +1,208 @@
+//! Queen domination: covering the board with as few queens as possible, so that every square
+//! holds a queen or is attacked by one. Independent domination asks for the same with queens
+//! that don't attack each other, which can take more of them: two queens cover a 4×4 board,
+//! but it takes three that leave each other alone.
+//!
+//! The search works by iterative deepening. Some queen has to cover the first square that's
+//! still uncovered, so it tries each square that would cover it in turn, then recurses on the
+//! next uncovered square. It starts from the lower bound of `(n - 1) / 2` queens, rounded up,
+//! that Raghavan and Venketesan (1987) proved, so the first placement it finds is minimal.
+
+use crate::bitboard::{with_row_set, RowSet};
+use crate::board::{Board, Queen, Square};
+use rayon::prelude::*;
+
+/// The fewest queens that dominate a board, found by [`Board::queen_domination`] or
+/// [`Board::independent_queen_domination`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Domination {
+    /// How many queens it takes.
+    pub queens: usize,
+    /// A board with that many queens on it that dominates it.
+    pub board: Board,
+}
+
+impl Board {
+    /// Whether every square of the board holds a queen or is attacked by one. Blocked squares
+    /// still have to be covered, and pawns block the queens' lines as usual.
+    pub fn is_dominated(&self) -> bool {
+        let squares = (0..self.height()).flat_map(|y| (0..self.width()).map(move |x| Square::new(x, y)));
+        if !self.pawns().is_empty() {
+            return squares.into_iter()
+                .all(|s| self.queens().iter().any(|&q| q == s || self.is_attacking(q, s)));
+        }
+
+        let diagonals = self.width() + self.height();
+        let mut rows = vec![false; self.height()];
+        let mut cols = vec![false; self.width()];
+        let mut sw_diagonals = vec![false; diagonals];
+        let mut se_diagonals = vec![false; diagonals];
+        for &queen in self.queens() {
+            let (sw, se) = self.diagonals(queen);
+            rows[queen.row()] = true;
+            cols[queen.col()] = true;
+            sw_diagonals[sw] = true;
+            se_diagonals[se] = true;
+        }
+        squares.into_iter().all(|s| {
+            let (sw, se) = self.diagonals(s);
+            rows[s.row()] || cols[s.col()] || sw_diagonals[sw] || se_diagonals[se]
+        })
+    }
+
+    /// The fewest queens that dominate a `side_size`×`side_size` board, along with a board
+    /// showing how. Practical up to about 11×11.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let domination = Board::queen_domination(8);
+    /// assert_eq!(domination.queens, 5);
+    /// assert!(domination.board.is_dominated());
+    /// ```
+    pub fn queen_domination(side_size: usize) -> Domination {
+        dominate(side_size, false)
+    }
+
+    /// The fewest queens that dominate a `side_size`×`side_size` board without any two of them
+    /// attacking each other, along with a board showing how. Practical up to about 11×11.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let domination = Board::independent_queen_domination(4);
+    /// assert_eq!(domination.queens, 3);
+    /// assert!(domination.board.is_dominated() && domination.board.is_valid());
+    /// ```
+    pub fn independent_queen_domination(side_size: usize) -> Domination {
+        dominate(side_size, true)
+    }
+}
+
+fn dominate(side_size: usize, independent: bool) -> Domination {
+    let queens = with_row_set!(side_size * side_size, |S| DominationSearch::<S>::new(side_size, independent).minimum());
+    let board = Board::with_queens(side_size, queens).expect("domination search placed a queen off the board");
+    Domination { queens: board.queens().len(), board }
+}
+
+/// A search over the squares of a board, numbered row by row from the top-left corner.
+struct DominationSearch<S> {
+    side_size: usize,
+    independent: bool,
+    /// For each square, the squares a queen on it covers, itself included.
+    covers: Vec<S>,
+    /// For each square, the squares a queen could cover it from.
+    coverers: Vec<Vec<usize>>,
+    /// The most squares a single queen covers.
+    max_cover: usize,
+}
+
+impl<S: RowSet> DominationSearch<S> {
+    fn new(side_size: usize, independent: bool) -> DominationSearch<S> {
+        let n = side_size;
+        let squares = n * n;
+        let board = Board::new(n);
+        let square = |i: usize| Square::new(i % n, i / n);
+        let covering = |a: Square, b: Square| {
+            a.row() == b.row() || a.col() == b.col() || board.diagonals(a).0 == board.diagonals(b).0
+                || board.diagonals(a).1 == board.diagonals(b).1
+        };
+
+        let mut covers = vec![S::empty(squares); squares];
+        let mut coverers = vec![vec![]; squares];
+        for (i, covered) in covers.iter_mut().enumerate() {
+            for j in (0..squares).filter(|&j| covering(square(i), square(j))) {
+                covered.insert(j);
+                coverers[j].push(i);
+            }
+        }
+        let max_cover = covers.iter().map(|c| c.len()).max().unwrap_or(0);
+        DominationSearch { side_size, independent, covers, coverers, max_cover }
+    }
+
+    /// The squares of a dominating placement with as few queens as possible.
+    fn minimum(&self) -> Vec<Queen> {
+        let n = self.side_size;
+        let all = S::full(n * n);
+        for queens in n.saturating_sub(1).div_ceil(2)..=n {
+            if let Some(squares) = self.place(&S::empty(n * n), &all, queens) {
+                return squares.into_iter().map(|i| Square::new(i % n, i / n)).collect();
+            }
+        }
+        unreachable!("{} queens in a row dominate any {}x{} board", n, n, n)
+    }
+
+    /// Places up to `queens_left` queens to cover the rest of `all`, given the squares already
+    /// `covered`. Returns the squares of the queens placed, or `None` if they can't do it.
+    /// Each way to cover the first square is tried in parallel.
+    fn place(&self, covered: &S, all: &S, queens_left: usize) -> Option<Vec<usize>> {
+        let first = match all.difference(covered).pop_first() {
+            Some(first) => first,
+            None => return Some(vec![]),
+        };
+        if queens_left == 0 {
+            return None;
+        }
+        self.coverers[first].par_iter()
+            .filter(|&&square| !self.independent || !covered.contains(square))
+            .find_map_first(|&square| {
+                let mut squares = self.place_from(&covered.union(&self.covers[square]), all, queens_left - 1)?;
+                squares.push(square);
+                Some(squares)
+            })
+    }
+
+    fn place_from(&self, covered: &S, all: &S, queens_left: usize) -> Option<Vec<usize>> {
+        let uncovered = all.difference(covered);
+        let first = match uncovered.clone().pop_first() {
+            Some(first) => first,
+            None => return Some(vec![]),
+        };
+        if uncovered.len() > queens_left * self.max_cover {
+            return None;
+        }
+        self.coverers[first].iter()
+            .filter(|&&square| !self.independent || !covered.contains(square))
+            .find_map(|&square| {
+                let mut squares = self.place_from(&covered.union(&self.covers[square]), all, queens_left - 1)?;
+                squares.push(square);
+                Some(squares)
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_queen_domination() {
+        // OEIS A075458 and A075324, from n = 1.
+        let domination = [1, 1, 1, 2, 3, 3, 4, 5, 5, 5, 5];
+        let independent = [1, 1, 1, 3, 3, 4, 4, 5, 5, 5, 5];
+        for side_size in 1..=11 {
+            let min = Board::queen_domination(side_size);
+            assert_eq!(min.queens, domination[side_size - 1], "side size {}", side_size);
+            assert!(min.board.is_dominated(), "side size {}", side_size);
+
+            let min = Board::independent_queen_domination(side_size);
+            assert_eq!(min.queens, independent[side_size - 1], "side size {}", side_size);
+            assert!(min.board.is_dominated() && min.board.is_valid(), "side size {}", side_size);
+        }
+        assert_eq!(Board::queen_domination(0), Domination { queens: 0, board: Board::new(0) });
+    }
+
+    #[test]
+    fn test_is_dominated() {
+        let board = Board::with_queens(4, vec![Queen::new(1, 1), Queen::new(2, 2)]).unwrap();
+        assert!(!board.is_dominated());
+        let board = Board::with_queens(4, vec![Queen::new(0, 1), Queen::new(2, 3)]).unwrap();
+        assert!(!board.is_dominated());
+        assert!(Board::queen_domination(4).board.is_dominated());
+
+        // A pawn in the way leaves the far end of the row uncovered.
+        let board = Board::rect(3, 1).with_added_queens(vec![Queen::new(0, 0)]).unwrap();
+        assert!(board.is_dominated());
+        assert!(!board.with_pawns(vec![Square::new(1, 0)]).unwrap().is_dominated());
+    }
+}
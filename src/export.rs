@@ -0,0 +1,191 @@
+//! Writing boards out for other programs to read, in one of a few line-based formats. Each
+//! format writes one board after another, so a stream of solutions can be written as it's found.
+//!
+//! - [`Format::Grid`] draws the boards the way [`Board::get_board_string`] does, with an empty
+//!   line after each.
+//! - [`Format::JsonLines`] writes one JSON object per line, such as
+//!   `{"board":1,"width":4,"height":4,"queens":[[0,1],[1,3],[2,0],[3,2]]}`, with each queen given
+//!   as `[column, row]`.
+//! - [`Format::Csv`] writes a `board,width,height,col,row` header and then one line per queen.
+//! - [`Format::Permutation`] writes the standard permutation notation, one board per line: the
+//!   row of each column's queen counted from 1, so the first solution of the 8-queens problem is
+//!   `15863724`. Boards more than 9 rows tall get their rows separated by spaces.
+//!
+//! Boards are numbered from 1 in the order they're written. Columns and rows are counted from 0
+//! at the top-left corner everywhere but in the permutation notation.
+
+use crate::board::Board;
+use std::io::{self, Write};
+
+/// A format to write boards in. See the [module docs](self) for what each looks like.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Format {
+    #[default]
+    Grid,
+    JsonLines,
+    Csv,
+    Permutation,
+}
+
+impl Board {
+    /// The row of each column's queen, from the leftmost column rightwards, or `None` unless
+    /// every column holds exactly one queen. The inverse of [`Board::from_rows`].
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let board = Board::from_rows(&[1, 3, 0, 2]).unwrap();
+    /// assert_eq!(board.rows(), Some(vec![1, 3, 0, 2]));
+    /// assert_eq!(Board::new(4).rows(), None);
+    /// ```
+    pub fn rows(&self) -> Option<Vec<usize>> {
+        let queens = self.queens();
+        if queens.len() != self.width() || queens.iter().enumerate().any(|(x, q)| q.x != x) {
+            return None;
+        }
+        Some(queens.iter().map(|q| q.y).collect())
+    }
+
+    /// The board in permutation notation, or `None` unless every column holds exactly one queen.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let board = nqueens::first_solution(8).unwrap();
+    /// assert_eq!(board.permutation_string().as_deref(), Some("15863724"));
+    /// let board = Board::from_rows(&[0, 2, 4, 1, 3, 8, 10, 12, 14, 5, 7, 9, 11, 13, 6]).unwrap();
+    /// assert_eq!(board.permutation_string().as_deref(), Some("1 3 5 2 4 9 11 13 15 6 8 10 12 14 7"));
+    /// ```
+    pub fn permutation_string(&self) -> Option<String> {
+        let rows: Vec<_> = self.rows()?.iter().map(|row| (row + 1).to_string()).collect();
+        let separator = if self.height() > 9 { " " } else { "" };
+        Some(rows.join(separator))
+    }
+
+    /// The board as a single-line JSON object numbered `board`, without a trailing newline.
+    pub fn to_json(&self, board: usize) -> String {
+        let queens: Vec<_> = self.queens().iter().map(|q| format!("[{},{}]", q.x, q.y)).collect();
+        format!(
+            "{{\"board\":{},\"width\":{},\"height\":{},\"queens\":[{}]}}",
+            board, self.width(), self.height(), queens.join(","),
+        )
+    }
+}
+
+/// Writes boards to `W` one after another in some [`Format`].
+///
+/// ```
+/// use nqueens::{Board, Format, SolutionWriter};
+///
+/// let mut writer = SolutionWriter::new(vec![], Format::Csv).unwrap();
+/// writer.write(&Board::from_rows(&[1, 0]).unwrap()).unwrap();
+/// let csv = String::from_utf8(writer.finish().unwrap()).unwrap();
+/// assert_eq!(csv, "board,width,height,col,row\n1,2,2,0,1\n1,2,2,1,0\n");
+/// ```
+#[derive(Debug)]
+pub struct SolutionWriter<W: Write> {
+    out: W,
+    format: Format,
+    boards: usize,
+}
+
+impl<W: Write> SolutionWriter<W> {
+    /// Starts writing to `out`, beginning with the header if the format has one.
+    pub fn new(mut out: W, format: Format) -> io::Result<SolutionWriter<W>> {
+        if format == Format::Csv {
+            writeln!(out, "board,width,height,col,row")?;
+        }
+        Ok(SolutionWriter { out, format, boards: 0 })
+    }
+
+    /// Writes the next board. Fails with [`io::ErrorKind::InvalidInput`] if the format is
+    /// [`Format::Permutation`] and the board doesn't hold exactly one queen per column, in which
+    /// case nothing is written.
+    pub fn write(&mut self, board: &Board) -> io::Result<()> {
+        let number = self.boards + 1;
+        match self.format {
+            Format::Grid => writeln!(self.out, "{}", board.get_board_string())?,
+            Format::JsonLines => writeln!(self.out, "{}", board.to_json(number))?,
+            Format::Csv => {
+                for queen in board.queens() {
+                    writeln!(self.out, "{},{},{},{},{}", number, board.width(), board.height(), queen.x, queen.y)?;
+                }
+            }
+            Format::Permutation => {
+                let permutation = board.permutation_string().ok_or_else(|| io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "permutation notation needs exactly one queen in every column",
+                ))?;
+                writeln!(self.out, "{}", permutation)?;
+            }
+        }
+        self.boards = number;
+        Ok(())
+    }
+
+    /// The number of boards written so far.
+    pub fn boards(&self) -> usize {
+        self.boards
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    /// Flushes the writer and hands back what it was writing to.
+    pub fn finish(mut self) -> io::Result<W> {
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Queen;
+
+    fn write_all(format: Format, boards: &[Board]) -> String {
+        let mut writer = SolutionWriter::new(vec![], format).unwrap();
+        for board in boards {
+            writer.write(board).unwrap();
+        }
+        assert_eq!(writer.boards(), boards.len());
+        String::from_utf8(writer.finish().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_formats() {
+        let boards: Vec<_> = Board::solutions(4).collect();
+        assert_eq!(write_all(Format::Permutation, &boards), "2413\n3142\n");
+        assert_eq!(
+            write_all(Format::JsonLines, &boards),
+            "{\"board\":1,\"width\":4,\"height\":4,\"queens\":[[0,1],[1,3],[2,0],[3,2]]}\n\
+             {\"board\":2,\"width\":4,\"height\":4,\"queens\":[[0,2],[1,0],[2,3],[3,1]]}\n",
+        );
+        let csv = write_all(Format::Csv, &boards);
+        assert_eq!(csv.lines().count(), 1 + 2 * 4);
+        assert!(csv.starts_with("board,width,height,col,row\n1,4,4,0,1\n"));
+        assert!(csv.ends_with("2,4,4,3,1\n"));
+        let grid = write_all(Format::Grid, &boards);
+        assert_eq!(grid, format!("{}\n{}\n", boards[0].get_board_string(), boards[1].get_board_string()));
+        assert_eq!(write_all(Format::Csv, &[]), "board,width,height,col,row\n");
+    }
+
+    #[test]
+    fn test_partial_boards() {
+        let board = Board::rect(3, 2).with_added_queens(vec![Queen::new(2, 1)]).unwrap();
+        assert_eq!(board.rows(), None);
+        assert_eq!(board.to_json(7), "{\"board\":7,\"width\":3,\"height\":2,\"queens\":[[2,1]]}");
+        assert_eq!(write_all(Format::Csv, std::slice::from_ref(&board)), "board,width,height,col,row\n1,3,2,2,1\n");
+
+        let mut writer = SolutionWriter::new(vec![], Format::Permutation).unwrap();
+        let error = writer.write(&board).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+        assert_eq!(writer.boards(), 0);
+        assert!(writer.finish().unwrap().is_empty());
+
+        // Tall boards can still hold one queen per column.
+        let board = Board::rect(2, 3).with_added_queens(vec![Queen::new(0, 2), Queen::new(1, 0)]).unwrap();
+        assert_eq!(board.permutation_string().as_deref(), Some("31"));
+    }
+}
@@ -0,0 +1,83 @@
+//! Solvers for the n-queens problem: placing `n` queens on an `n`×`n` chessboard so that no two
+//! queens attack each other.
+//!
+//! ```
+//! use nqueens::{Board, Queen};
+//!
+//! let board = Board::new(4)
+//!     .try_insert_queen(Queen::new(0, 1)).unwrap()
+//!     .try_insert_queen(Queen::new(1, 3)).unwrap();
+//! assert!(board.is_valid());
+//! assert!(board.try_insert_queen(Queen::new(2, 2)).is_none());
+//!
+//! assert_eq!(nqueens::count_solutions(8), 92);
+//! ```
+
+mod binary;
+mod bitboard;
+mod board;
+mod checkpoint;
+mod collinear;
+mod completion;
+mod construct;
+mod cube;
+mod domination;
+mod export;
+mod local_search;
+mod mask;
+mod parse;
+mod pawns;
+mod pieces;
+mod search;
+mod symmetry;
+mod toroidal;
+mod validate;
+mod weighted;
+
+pub use crate::binary::{BinaryReader, BinaryWriter};
+pub use crate::board::{Board, Geometry, Queen, Square};
+pub use crate::checkpoint::{Checkpoint, ParseCheckpointError};
+pub use crate::collinear::{
+    count_no_three_in_line_solutions,
+    first_no_three_in_line_solution,
+    for_each_no_three_in_line_solution,
+};
+pub use crate::completion::CompletionError;
+pub use crate::cube::{Cell, Cube, MaxCubeQueens, Queen3};
+pub use crate::domination::Domination;
+pub use crate::export::{Format, SolutionWriter};
+pub use crate::local_search::{MinConflicts, MinConflictsGaveUp, MinConflictsSolution};
+pub use crate::mask::MaskError;
+pub use crate::parse::ParseBoardError;
+pub use crate::pawns::MaxQueens;
+pub use crate::pieces::{
+    Amazon,
+    Bishop,
+    King,
+    Knight,
+    Piece,
+    Rook,
+    SuperQueen,
+    count_piece_placements,
+    for_each_piece_placement,
+};
+pub use crate::search::{
+    Backend,
+    SearchMode,
+    Solutions,
+    count_placements,
+    count_placements_table,
+    count_solutions,
+    count_solutions_with,
+    count_solutions_with_mode,
+    find_first_valid_board,
+    find_valid_boards,
+    first_solution,
+    first_solution_with,
+    for_each_solution,
+    for_each_solution_with,
+};
+pub use crate::symmetry::{FundamentalSolution, FundamentalSolutions, Symmetry};
+pub use crate::toroidal::count_toroidal_solutions;
+pub use crate::validate::{Conflict, Line};
+pub use crate::weighted::{Objective, WeightedSolution, Weights};
@@ -0,0 +1,261 @@
+//! Min-conflicts local search. Rather than building a solution column by column, start from a
+//! full placement with one queen per row and column and repair it: repeatedly take a queen that's
+//! under attack and swap its row with another queen's, keeping the swap unless it leaves more
+//! queens attacking each other. This is the iterative repair of Sosič and Gu (1991). It finds
+//! solutions for boards with millions of columns, where backtracking never gets anywhere, but it
+//! can't tell that a board has no solution and never finds more than one.
+
+use crate::board::{Board, Queen};
+
+/// Settings for [`MinConflicts::solve`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MinConflicts {
+    /// Seeds the random number generator, so runs with the same settings give the same board.
+    pub seed: u64,
+    /// How many swaps to try before giving up on a placement and starting over.
+    pub max_steps: usize,
+    /// How many times to start over before giving up entirely.
+    pub max_restarts: usize,
+}
+
+/// A solution found by [`MinConflicts::solve`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MinConflictsSolution {
+    pub board: Board,
+    /// The number of swaps tried, counting every attempt.
+    pub steps: usize,
+    /// The number of times the search started over from a new placement.
+    pub restarts: usize,
+}
+
+/// Returned by [`MinConflicts::solve`] when every attempt ran out of steps.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct MinConflictsGaveUp {
+    pub steps: usize,
+    pub restarts: usize,
+}
+
+impl Default for MinConflicts {
+    fn default() -> MinConflicts {
+        MinConflicts {
+            seed: 0,
+            max_steps: 100_000,
+            max_restarts: 10,
+        }
+    }
+}
+
+impl MinConflicts {
+    /// Searches for a solution of the `side_size`-queens problem.
+    ///
+    /// ```
+    /// use nqueens::MinConflicts;
+    ///
+    /// let solution = MinConflicts { seed: 7, ..MinConflicts::default() }.solve(10_000).unwrap();
+    /// assert!(solution.board.is_complete() && solution.board.is_valid());
+    /// ```
+    pub fn solve(&self, side_size: usize) -> Result<MinConflictsSolution, MinConflictsGaveUp> {
+        let mut rng = SplitMix64::new(self.seed);
+        let mut steps = 0;
+        for restarts in 0..=self.max_restarts {
+            let mut placement = Placement::random(side_size, &mut rng);
+            if placement.repair(self.max_steps, &mut steps, &mut rng) {
+                let board = Board::from_rows(&placement.rows).expect("placement row out of range");
+                return Ok(MinConflictsSolution { board, steps, restarts });
+            }
+        }
+        Err(MinConflictsGaveUp { steps, restarts: self.max_restarts })
+    }
+}
+
+/// How many random rows the initial placement tries for each column before settling for one
+/// that's attacked.
+const INITIAL_TRIES: usize = 128;
+
+/// One queen per column on distinct rows, with counters of how many queens occupy each diagonal.
+/// Since no two queens ever share a row, the only attacks are along diagonals.
+struct Placement {
+    side_size: usize,
+    rows: Vec<usize>,
+    sw_counts: Vec<usize>,
+    se_counts: Vec<usize>,
+    /// The sum over all diagonals of the number of queens on it past the first.
+    collisions: usize,
+}
+
+impl Placement {
+    /// Places the queens column by column on distinct random rows, preferring rows whose
+    /// diagonals are still empty. That leaves few enough collisions for the repair to finish
+    /// quickly, even on huge boards.
+    fn random(side_size: usize, rng: &mut SplitMix64) -> Placement {
+        let diagonals = (2 * side_size).saturating_sub(1);
+        let mut placement = Placement {
+            side_size,
+            rows: Vec::with_capacity(side_size),
+            sw_counts: vec![0; diagonals],
+            se_counts: vec![0; diagonals],
+            collisions: 0,
+        };
+
+        let mut free_rows: Vec<usize> = (0..side_size).collect();
+        for x in 0..side_size {
+            let mut pick = 0;
+            for _ in 0..INITIAL_TRIES {
+                pick = rng.below(free_rows.len());
+                if !placement.is_attacked(Queen::new(x, free_rows[pick]), 0) {
+                    break;
+                }
+            }
+            let row = free_rows.swap_remove(pick);
+            placement.rows.push(row);
+            placement.add(Queen::new(x, row));
+        }
+        placement
+    }
+
+    fn add(&mut self, queen: Queen) {
+        for count in [
+            &mut self.sw_counts[queen.sw_diagonal(self.side_size)],
+            &mut self.se_counts[queen.se_diagonal(self.side_size)],
+        ] {
+            if *count > 0 {
+                self.collisions += 1;
+            }
+            *count += 1;
+        }
+    }
+
+    fn remove(&mut self, queen: Queen) {
+        for count in [
+            &mut self.sw_counts[queen.sw_diagonal(self.side_size)],
+            &mut self.se_counts[queen.se_diagonal(self.side_size)],
+        ] {
+            *count -= 1;
+            if *count > 0 {
+                self.collisions -= 1;
+            }
+        }
+    }
+
+    /// Whether a queen on `queen`'s square shares a diagonal with another queen, given that
+    /// `already_there` queens are on the square itself.
+    fn is_attacked(&self, queen: Queen, already_there: usize) -> bool {
+        self.sw_counts[queen.sw_diagonal(self.side_size)] > already_there
+            || self.se_counts[queen.se_diagonal(self.side_size)] > already_there
+    }
+
+    fn is_col_attacked(&self, x: usize) -> bool {
+        self.is_attacked(Queen::new(x, self.rows[x]), 1)
+    }
+
+    fn attacked_cols(&self) -> Vec<usize> {
+        (0..self.side_size).filter(|&x| self.is_col_attacked(x)).collect()
+    }
+
+    fn swap(&mut self, a: usize, b: usize) {
+        let (row_a, row_b) = (self.rows[a], self.rows[b]);
+        self.remove(Queen::new(a, row_a));
+        self.remove(Queen::new(b, row_b));
+        self.add(Queen::new(a, row_b));
+        self.add(Queen::new(b, row_a));
+        self.rows.swap(a, b);
+    }
+
+    /// Repeatedly picks an attacked queen and swaps its row with a random other queen's, undoing
+    /// the swap if it added collisions. Stops once there are no collisions left or `max_steps`
+    /// swaps have been tried, adding the tries to `steps`. Returns whether the placement is now a
+    /// solution.
+    fn repair(&mut self, max_steps: usize, steps: &mut usize, rng: &mut SplitMix64) -> bool {
+        let mut attacked = self.attacked_cols();
+        let mut steps_left = max_steps;
+        while self.collisions > 0 {
+            if attacked.is_empty() {
+                // A swap can put queens that weren't attacked before under attack, and we
+                // only keep track of the two queens that moved.
+                attacked = self.attacked_cols();
+            }
+
+            let a = attacked.swap_remove(rng.below(attacked.len()));
+            if !self.is_col_attacked(a) {
+                continue;
+            }
+            if steps_left == 0 {
+                return false;
+            }
+            steps_left -= 1;
+            *steps += 1;
+
+            // Any other column; an attacked queen means there are at least two.
+            let b = rng.below(self.side_size - 1);
+            let b = if b >= a { b + 1 } else { b };
+            let collisions = self.collisions;
+            self.swap(a, b);
+            if self.collisions > collisions {
+                self.swap(a, b);
+            }
+            for x in [a, b] {
+                if self.is_col_attacked(x) {
+                    attacked.push(x);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// The SplitMix64 generator. Tiny, fast and plenty random for shuffling queens around.
+struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    fn new(seed: u64) -> SplitMix64 {
+        SplitMix64 { state: seed }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// A random number in `0..bound`. `bound` must not be 0.
+    fn below(&mut self, bound: usize) -> usize {
+        ((self.next_u64() as u128 * bound as u128) >> 64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_min_conflicts() {
+        for side_size in (0..=40).filter(|&n| n != 2 && n != 3) {
+            let solution = MinConflicts::default().solve(side_size).unwrap();
+            assert!(solution.board.is_complete(), "side size {}", side_size);
+            assert!(solution.board.is_valid(), "side size {}", side_size);
+        }
+    }
+
+    #[test]
+    fn test_min_conflicts_is_seeded() {
+        let settings = MinConflicts { seed: 42, ..MinConflicts::default() };
+        assert_eq!(settings.solve(200), settings.solve(200));
+    }
+
+    #[test]
+    fn test_min_conflicts_gives_up() {
+        let settings = MinConflicts { seed: 1, max_steps: 50, max_restarts: 3 };
+        let gave_up = settings.solve(3).unwrap_err();
+        assert_eq!(gave_up, MinConflictsGaveUp { steps: 200, restarts: 3 });
+    }
+
+    #[test]
+    fn test_min_conflicts_large() {
+        let solution = MinConflicts { seed: 3, ..MinConflicts::default() }.solve(1_000_000).unwrap();
+        assert!(solution.board.is_complete() && solution.board.is_valid());
+    }
+}
@@ -1,12 +1,957 @@
 use std::sync::{Arc, Mutex, Condvar, atomic::{AtomicUsize, Ordering}};
-use std::thread;
+use std::io::{self, Read, Write};
+use std::{env, fmt, fs, process, thread};
 use std::time::{Instant, Duration};
 use crossterm::{cursor, terminal};
 use rayon::prelude::*;
+use nqueens::{Backend, BinaryReader, BinaryWriter, Board, Checkpoint, Format, Geometry, SolutionWriter, Square};
+
+const USAGE: &str = "\
+usage: nqueens [OPTIONS] [SIZES]
+       nqueens count [OPTIONS] [SIZES]
+       nqueens validate [--toroidal] FILE
+       nqueens convert [--format NAME] [--toroidal] INPUT OUTPUT
+
+SIZES is a single board size (`12`), a range (`8..14`, `8..=14`) or an
+open range (`4..`). Defaults to `4..`, which runs until interrupted. With
+`--height`, the sizes are the widths of the boards.
+
+`count` prints the exact number of solutions for each size, one
+`SIZE COUNT` line per size, without building any boards. With `--table`,
+it prints a `SIZE K COUNT` line for every number of queens K instead.
+
+`validate` reads a board from FILE, or stdin if FILE is `-`, drawn as a
+grid of `QQ`, `PP`, `##` and `__` squares, in permutation notation
+(`15863724`) or in FEN-like rank notation (`Q7/6Q1/...`). It draws the
+board with the queens that break the rules marked `XX`, lists what's
+wrong and exits with status 1 if the board isn't valid.
+
+`convert` turns a binary solution file written with `--format binary`
+into text in the `--format` given, `permutation` by default, and text
+into a binary file, or into another text format if `--format` says so.
+Text is read as boards in permutation or FEN-like notation, one per line,
+or as grids separated by empty lines, as `convert --format grid` draws
+them. All the boards of a binary file are the same size; `--toroidal`
+marks the ones converted from text as toroidal. INPUT and text OUTPUT can
+be `-` for stdin and stdout.
+
+options:
+    --count-only     same as `count`
+    --table          count the placements of every number of queens, from
+                     none up to one per column or row, in a single search
+    --first-only     stop after the first solution of each size
+    --no-tui         print plain text instead of redrawing the terminal
+    --fundamental    only count or print one board out of each set of
+                     boards that are rotations or reflections of each
+                     other. Implies `--no-tui`
+    -j, --threads N  number of worker threads (default: one per core)
+    --backend NAME   search engine used to count boards and to find them for
+                     `--first-only` and the TUI: `board`, `bits64`,
+                     `bits128`, `wide` or `auto` (default). `--no-tui`
+                     listings always walk the boards in order.
+    --mask FILE      solve the board drawn in FILE instead, one line per row
+                     with `.` for open squares, `#` for blocked ones and `Q`
+                     for queens that have to stay. Sets the board size
+    --block COL,ROW  block a square on every board, counting from 0 at the
+                     top-left corner. Can be repeated
+    --toroidal       wrap the boards around at their edges, so that the
+                     diagonals continue on the opposite side
+    --height N       use boards N rows tall instead of square ones
+    --queens K       place K queens instead of one per column or row,
+                     whichever there are fewer of
+    --no-three-in-line
+                     only count or print the boards where no three queens
+                     lie on a common straight line, of any slope
+    --format NAME    how to print the boards: `grid` (default), `jsonl` for
+                     one JSON object per board, `csv` for one line per
+                     queen or `permutation` for the row of each column's
+                     queen counted from 1, as in `15863724`. `binary`
+                     packs each board into a few bytes, with an index for
+                     reading any of them back; it needs `--output` and a
+                     single size of square board, and can't be combined
+                     with `--mask` or `--block`. Formats other than
+                     `grid` print nothing but the boards and imply
+                     `--no-tui`
+    -o, --output FILE
+                     write the boards to FILE instead of stdout. Implies
+                     `--no-tui`
+    --checkpoint FILE
+                     save the progress of `count` to FILE every second or
+                     so, and carry on from FILE if it's already there, so
+                     that a long count can be stopped and picked up later
+    -h, --help       print this message
+
+`--mask`, `--block`, `--toroidal`, `--height` and `--queens` can't be
+combined with `--fundamental` or `--backend`, and `--queens` can't be
+combined with `--mask`, `--block` or `--toroidal`. Toroidal boards must be
+square. `--no-three-in-line` can't be combined with any of them, nor with
+`--fundamental`, `--backend` or `--table`. `--format` and `--output` can't
+be combined with `count`. `--checkpoint` only works with `count` of a
+single size, without any other options than `-j`.
+";
 
 fn main() {
+    let mut command = match Command::parse(env::args().skip(1)) {
+        Ok(command) => command,
+        Err(ArgsError::Help) => {
+            print!("{}", USAGE);
+            return;
+        }
+        Err(ArgsError::Invalid(message)) => {
+            eprintln!("error: {}\n\n{}", message, USAGE);
+            process::exit(2);
+        }
+    };
+    if let Some(threads) = command.options().and_then(|options| options.threads) {
+        if let Err(e) = rayon::ThreadPoolBuilder::new().num_threads(threads).build_global() {
+            exit_with_error(format!("could not start thread pool: {}", e));
+        }
+    }
+    if let Some(options) = command.options_mut() {
+        options.load_mask();
+    }
+
+    match command {
+        Command::Count(options) => run_count(&options),
+        Command::Solve(options) if options.first_only || options.no_tui || options.fundamental || options.has_output() => {
+            run_plain(&options)
+        }
+        Command::Solve(options) => run_tui(&options),
+        Command::Validate(validate) => run_validate(&validate),
+        Command::Convert(convert) => run_convert(&convert),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Command {
+    /// Find boards, drawing them in the TUI or printing them as text.
+    Solve(Options),
+    /// Print the number of boards of each size.
+    Count(Options),
+    /// Check a board read from a file and explain what's wrong with it.
+    Validate(Validate),
+    /// Turn a binary solution file into text or the other way around.
+    Convert(Convert),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Validate {
+    /// The file to read the board from, or `-` for stdin.
+    path: String,
+    toroidal: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Convert {
+    /// The file to read boards from, or `-` for stdin.
+    input: String,
+    /// The file to write boards to, or `-` for stdout.
+    output: String,
+    /// The text format to write, `Some(None)` for the binary format or `None` to pick whichever
+    /// the input isn't.
+    format: Option<Option<Format>>,
+    toroidal: bool,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sizes {
+    start: usize,
+    /// Inclusive upper bound, or `None` to keep going forever.
+    end: Option<usize>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Options {
+    sizes: Sizes,
+    count_only: bool,
+    table: bool,
+    first_only: bool,
+    no_tui: bool,
+    fundamental: bool,
+    threads: Option<usize>,
+    backend: Backend,
+    /// The path of the `--mask` file.
+    mask: Option<String>,
+    blocked: Vec<Square>,
+    toroidal: bool,
+    /// The board read from the `--mask` file, once it's been loaded.
+    board: Option<Board>,
+    height: Option<usize>,
+    queens: Option<usize>,
+    no_three_in_line: bool,
+    format: Format,
+    /// Whether `--format binary` was given, in which case `format` is left as the grid.
+    binary: bool,
+    /// The path of the `--output` file.
+    output: Option<String>,
+    /// The path of the `--checkpoint` file.
+    checkpoint: Option<String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum ArgsError {
+    Help,
+    Invalid(String),
+}
+
+impl Sizes {
+    fn parse(s: &str) -> Result<Sizes, ArgsError> {
+        let parse_size = |s: &str| s.parse::<usize>()
+            .map_err(|_| ArgsError::Invalid(format!("invalid board size `{}`", s)));
+
+        let sizes = if let Some(i) = s.find("..") {
+            let start = parse_size(&s[..i])?;
+            let rest = &s[i + 2..];
+            let end = if let Some(end) = rest.strip_prefix('=') {
+                Some(parse_size(end)?)
+            } else if rest.is_empty() {
+                None
+            } else {
+                match parse_size(rest)?.checked_sub(1) {
+                    Some(end) => Some(end),
+                    None => return Err(ArgsError::Invalid(format!("empty size range `{}`", s))),
+                }
+            };
+            Sizes { start, end }
+        } else {
+            let size = parse_size(s)?;
+            Sizes { start: size, end: Some(size) }
+        };
+
+        match sizes.end {
+            Some(end) if end < sizes.start => Err(ArgsError::Invalid(format!("empty size range `{}`", s))),
+            _ => Ok(sizes),
+        }
+    }
+
+    fn iter(self) -> impl Iterator<Item=usize> {
+        let end = self.end;
+        (self.start..).take_while(move |&side_size| end.is_none_or(|end| side_size <= end))
+    }
+
+    fn is_finite(self) -> bool {
+        self.end.is_some()
+    }
+}
+
+impl Command {
+    fn parse(args: impl IntoIterator<Item=String>) -> Result<Command, ArgsError> {
+        let mut args = args.into_iter().peekable();
+        if args.peek().map(|arg| arg == "validate").unwrap_or(false) {
+            args.next();
+            return Validate::parse(args).map(Command::Validate);
+        }
+        if args.peek().map(|arg| arg == "convert").unwrap_or(false) {
+            args.next();
+            return Convert::parse(args).map(Command::Convert);
+        }
+        let count = args.peek().map(|arg| arg == "count").unwrap_or(false);
+        if count {
+            args.next();
+        }
+
+        let mut options = Options::parse(args)?;
+        if count {
+            if options.first_only {
+                return Err(ArgsError::Invalid("`count` can't be combined with `--first-only`".to_string()));
+            }
+            if options.has_output() {
+                return Err(ArgsError::Invalid("`count` can't be combined with `--format` or `--output`".to_string()));
+            }
+            options.count_only = true;
+        }
+        if options.checkpoint.is_some() && !options.count_only {
+            return Err(ArgsError::Invalid("`--checkpoint` only works with `count`".to_string()));
+        }
+        if options.table && !options.count_only {
+            return Err(ArgsError::Invalid("`--table` only works with `count`".to_string()));
+        }
+
+        if options.count_only {
+            Ok(Command::Count(options))
+        } else {
+            Ok(Command::Solve(options))
+        }
+    }
+
+    /// The options of the commands that search for boards.
+    fn options(&self) -> Option<&Options> {
+        match self {
+            Command::Solve(options) | Command::Count(options) => Some(options),
+            Command::Validate(_) | Command::Convert(_) => None,
+        }
+    }
+
+    fn options_mut(&mut self) -> Option<&mut Options> {
+        match self {
+            Command::Solve(options) | Command::Count(options) => Some(options),
+            Command::Validate(_) | Command::Convert(_) => None,
+        }
+    }
+}
+
+impl Validate {
+    fn parse(args: impl IntoIterator<Item=String>) -> Result<Validate, ArgsError> {
+        let mut path = None;
+        let mut toroidal = false;
+        for arg in args {
+            match &*arg {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--toroidal" => toroidal = true,
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(ArgsError::Invalid(format!("unknown option `{}` for `validate`", arg)));
+                }
+                _ if path.is_some() => return Err(ArgsError::Invalid(format!("unexpected argument `{}`", arg))),
+                _ => path = Some(arg),
+            }
+        }
+        let path = path.ok_or_else(|| ArgsError::Invalid("`validate` expects a file".to_string()))?;
+        Ok(Validate { path, toroidal })
+    }
+}
+
+impl Convert {
+    fn parse(args: impl IntoIterator<Item=String>) -> Result<Convert, ArgsError> {
+        let mut paths = vec![];
+        let mut format = None;
+        let mut toroidal = false;
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match &*arg {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--toroidal" => toroidal = true,
+                "--format" => {
+                    let name = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a format name", arg)))?;
+                    format = Some(if name == "binary" { None } else { Some(parse_format(&name)?) });
+                }
+                _ if arg.starts_with('-') && arg != "-" => {
+                    return Err(ArgsError::Invalid(format!("unknown option `{}` for `convert`", arg)));
+                }
+                _ if paths.len() == 2 => return Err(ArgsError::Invalid(format!("unexpected argument `{}`", arg))),
+                _ => paths.push(arg),
+            }
+        }
+        let output = paths.pop();
+        let (input, output) = match (paths.pop(), output) {
+            (Some(input), Some(output)) => (input, output),
+            _ => return Err(ArgsError::Invalid("`convert` expects an input file and an output file".to_string())),
+        };
+        Ok(Convert { input, output, format, toroidal })
+    }
+}
+
+impl Options {
+    fn parse(args: impl IntoIterator<Item=String>) -> Result<Options, ArgsError> {
+        let mut options = Options {
+            sizes: Sizes { start: 4, end: None },
+            count_only: false,
+            table: false,
+            first_only: false,
+            no_tui: false,
+            fundamental: false,
+            threads: None,
+            backend: Backend::Auto,
+            mask: None,
+            blocked: vec![],
+            toroidal: false,
+            board: None,
+            height: None,
+            queens: None,
+            no_three_in_line: false,
+            format: Format::Grid,
+            binary: false,
+            output: None,
+            checkpoint: None,
+        };
+        let mut sizes = None;
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            match &*arg {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--count-only" => options.count_only = true,
+                "--table" => options.table = true,
+                "--first-only" => options.first_only = true,
+                "--no-tui" => options.no_tui = true,
+                "--fundamental" => options.fundamental = true,
+                "--toroidal" => options.toroidal = true,
+                "--no-three-in-line" => options.no_three_in_line = true,
+                "-j" | "--threads" => {
+                    let threads = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a thread count", arg)))?;
+                    options.threads = match threads.parse() {
+                        Ok(0) | Err(_) => return Err(ArgsError::Invalid(format!("invalid thread count `{}`", threads))),
+                        Ok(threads) => Some(threads),
+                    };
+                }
+                "--backend" => {
+                    let backend = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a backend name", arg)))?;
+                    options.backend = parse_backend(&backend)?;
+                }
+                "--format" => {
+                    let format = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a format name", arg)))?;
+                    options.binary = format == "binary";
+                    if !options.binary {
+                        options.format = parse_format(&format)?;
+                    }
+                }
+                "-o" | "--output" => {
+                    let path = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a file", arg)))?;
+                    options.output = Some(path);
+                }
+                "--checkpoint" => {
+                    let path = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a file", arg)))?;
+                    options.checkpoint = Some(path);
+                }
+                "--mask" => {
+                    let path = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a file", arg)))?;
+                    options.mask = Some(path);
+                }
+                "--block" => {
+                    let square = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a square", arg)))?;
+                    options.blocked.push(parse_square(&square)?);
+                }
+                "--height" | "--queens" => {
+                    let count = args.next()
+                        .ok_or_else(|| ArgsError::Invalid(format!("`{}` expects a number", arg)))?;
+                    let count = count.parse()
+                        .map_err(|_| ArgsError::Invalid(format!("invalid number `{}` for `{}`", count, arg)))?;
+                    if arg == "--height" {
+                        options.height = Some(count);
+                    } else {
+                        options.queens = Some(count);
+                    }
+                }
+                _ if arg.starts_with('-') => return Err(ArgsError::Invalid(format!("unknown option `{}`", arg))),
+                _ if sizes.is_some() => return Err(ArgsError::Invalid(format!("unexpected argument `{}`", arg))),
+                _ => sizes = Some(Sizes::parse(&arg)?),
+            }
+        }
+
+        if options.count_only && options.first_only {
+            return Err(ArgsError::Invalid("`--count-only` and `--first-only` can't be combined".to_string()));
+        }
+        if options.count_only && options.has_output() {
+            return Err(ArgsError::Invalid("`--format` and `--output` can't be combined with `--count-only`".to_string()));
+        }
+        if options.has_layout() || options.has_shape() {
+            let variants = "`--mask`, `--block`, `--toroidal`, `--height` or `--queens`";
+            if options.fundamental {
+                return Err(ArgsError::Invalid(format!("`--fundamental` can't be combined with {}", variants)));
+            }
+            if options.backend != Backend::Auto {
+                return Err(ArgsError::Invalid(format!("`--backend` can't be combined with {}", variants)));
+            }
+        }
+        if options.table && (options.has_layout() || options.queens.is_some() || options.fundamental) {
+            return Err(ArgsError::Invalid(
+                "`--table` can't be combined with `--mask`, `--block`, `--toroidal`, `--queens` or `--fundamental`".to_string(),
+            ));
+        }
+        if options.table && options.backend != Backend::Auto {
+            return Err(ArgsError::Invalid("`--table` can't be combined with `--backend`".to_string()));
+        }
+        if options.has_layout() && options.queens.is_some() {
+            return Err(ArgsError::Invalid("`--queens` can't be combined with `--mask`, `--block` or `--toroidal`".to_string()));
+        }
+        if options.no_three_in_line
+            && (options.has_layout() || options.has_shape() || options.fundamental || options.table || options.backend != Backend::Auto)
+        {
+            return Err(ArgsError::Invalid(
+                "`--no-three-in-line` only works on plain square boards, without `--fundamental`, `--backend` or `--table`".to_string(),
+            ));
+        }
+        let single_size = sizes.is_some_and(|sizes| sizes.end == Some(sizes.start));
+        if options.binary && (options.output.is_none() || options.has_shape() || !single_size) {
+            return Err(ArgsError::Invalid(
+                "`--format binary` needs `--output` and a single size of square board, without `--height` or `--queens`".to_string(),
+            ));
+        }
+        // Binary files only keep the queens and whether the board is toroidal.
+        if options.binary && (options.mask.is_some() || !options.blocked.is_empty()) {
+            return Err(ArgsError::Invalid("`--format binary` can't be combined with `--mask` or `--block`".to_string()));
+        }
+        let variant = options.has_layout() || options.has_shape() || options.no_three_in_line || options.fundamental || options.table;
+        if options.checkpoint.is_some() && (variant || options.backend != Backend::Auto || !single_size) {
+            return Err(ArgsError::Invalid(
+                "`--checkpoint` only works with a single size of plain square board, without any other options than `-j`".to_string(),
+            ));
+        }
+        if options.toroidal && options.height.is_some() {
+            return Err(ArgsError::Invalid("`--toroidal` boards are square, so it can't be combined with `--height`".to_string()));
+        }
+        if options.mask.is_some() && (sizes.is_some() || options.height.is_some()) {
+            return Err(ArgsError::Invalid("`--mask` sets the board size, so it can't be combined with SIZES or `--height`".to_string()));
+        }
+        if let Some(sizes) = sizes {
+            options.sizes = sizes;
+        }
+        if options.mask.is_none() {
+            let (width, height) = (options.sizes.start, options.height(options.sizes.start));
+            if let Some(square) = options.blocked.iter().find(|s| s.x >= width || s.y >= height) {
+                return Err(ArgsError::Invalid(format!(
+                    "blocked square `{},{}` lies off the {}x{} board",
+                    square.x, square.y, width, height,
+                )));
+            }
+        }
+        Ok(options)
+    }
+
+    /// Whether the boards have blocked squares or queens on them from the start or wrap around,
+    /// rather than being empty and flat.
+    fn has_layout(&self) -> bool {
+        self.mask.is_some() || !self.blocked.is_empty() || self.toroidal
+    }
+
+    fn geometry(&self) -> Geometry {
+        if self.toroidal {
+            Geometry::Toroidal
+        } else {
+            Geometry::Flat
+        }
+    }
+
+    /// Reads the `--mask` file, if there is one, and sets the board and its size from it.
+    fn load_mask(&mut self) {
+        let path = match &self.mask {
+            Some(path) => path,
+            None => return,
+        };
+        let mask = fs::read_to_string(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+        let board = Board::from_mask(&mask).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+        let board = board.with_blocked_squares(self.blocked.iter().copied())
+            .unwrap_or_else(|| exit_with_error("`--block` square lies off the board from `--mask`"));
+        let board = board.with_geometry(self.geometry())
+            .unwrap_or_else(|| exit_with_error(format!("{}: `--toroidal` needs a square board", path)));
+        self.sizes = Sizes { start: board.width(), end: Some(board.width()) };
+        self.board = Some(board);
+    }
+
+    /// Whether the boards go somewhere other than stdout or in a format other than the grid.
+    fn has_output(&self) -> bool {
+        self.format != Format::Grid || self.binary || self.output.is_some()
+    }
+
+    /// Whether the boards aren't square or get a different number of queens than usual.
+    fn has_shape(&self) -> bool {
+        self.height.is_some() || self.queens.is_some()
+    }
+
+    /// The height of the boards `width` wide.
+    fn height(&self, width: usize) -> usize {
+        match (&self.board, self.height) {
+            (Some(board), _) => board.height(),
+            (None, Some(height)) => height,
+            (None, None) => width,
+        }
+    }
+
+    /// The number of queens to place on boards `width` wide.
+    fn queens(&self, width: usize) -> usize {
+        self.queens.unwrap_or_else(|| width.min(self.height(width)))
+    }
+
+    /// The board to solve for `width`, with any blocked squares and fixed queens on it.
+    fn base_board(&self, width: usize) -> Board {
+        match &self.board {
+            Some(board) => board.clone(),
+            None => Board::rect(width, self.height(width))
+                .with_blocked_squares(self.blocked.iter().copied())
+                .and_then(|board| board.with_geometry(self.geometry()))
+                .expect("blocked square off the board or toroidal board not square"),
+        }
+    }
+}
+
+fn parse_backend(s: &str) -> Result<Backend, ArgsError> {
+    match s {
+        "board" => Ok(Backend::Board),
+        "bits64" => Ok(Backend::Bits64),
+        "bits128" => Ok(Backend::Bits128),
+        "wide" => Ok(Backend::BitsWide),
+        "auto" => Ok(Backend::Auto),
+        _ => Err(ArgsError::Invalid(format!("unknown backend `{}`", s))),
+    }
+}
+
+fn parse_format(s: &str) -> Result<Format, ArgsError> {
+    match s {
+        "grid" => Ok(Format::Grid),
+        "jsonl" => Ok(Format::JsonLines),
+        "csv" => Ok(Format::Csv),
+        "permutation" => Ok(Format::Permutation),
+        _ => Err(ArgsError::Invalid(format!("unknown format `{}`", s))),
+    }
+}
+
+fn parse_square(s: &str) -> Result<Square, ArgsError> {
+    let invalid = || ArgsError::Invalid(format!("invalid square `{}`, expected COL,ROW", s));
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    let x = x.trim().parse().map_err(|_| invalid())?;
+    let y = y.trim().parse().map_err(|_| invalid())?;
+    Ok(Square::new(x, y))
+}
+
+/// Names the size of a board: its side for square boards, or its width and height.
+fn size_name(width: usize, height: usize) -> String {
+    if width == height {
+        width.to_string()
+    } else {
+        format!("{}x{}", width, height)
+    }
+}
+
+/// Prints `error` and exits with a failure status.
+fn exit_with_error(error: impl fmt::Display) -> ! {
+    eprintln!("error: {}", error);
+    process::exit(1);
+}
+
+fn run_count(options: &Options) {
+    if let Some(path) = &options.checkpoint {
+        return run_checkpointed(options, path);
+    }
+    let stdout = io::stdout();
+    for side_size in options.sizes.iter() {
+        let height = options.height(side_size);
+        if options.table {
+            let mut out = stdout.lock();
+            for (queens, num_boards) in nqueens::count_placements_table(side_size, height).into_iter().enumerate() {
+                writeln!(out, "{} {} {}", size_name(side_size, height), queens, num_boards).expect("failed to write to stdout");
+            }
+            continue;
+        }
+        let num_boards = if options.has_layout() {
+            options.base_board(side_size).count_completions().unwrap_or_else(|e| exit_with_error(e))
+        } else if options.has_shape() {
+            nqueens::count_placements(side_size, height, options.queens(side_size))
+        } else if options.no_three_in_line {
+            nqueens::count_no_three_in_line_solutions(side_size)
+        } else if options.fundamental {
+            Board::count_fundamental_solutions(side_size)
+        } else {
+            nqueens::count_solutions_with(side_size, options.backend)
+        };
+        writeln!(stdout.lock(), "{} {}", size_name(side_size, height), num_boards).expect("failed to write to stdout");
+    }
+}
+
+/// How many columns deep the work units of `--checkpoint` counts go. For 20 queens that makes
+/// about 2,500 units.
+const CHECKPOINT_DEPTH: usize = 3;
+
+/// Counts the single size asked for, saving the progress to the `--checkpoint` file at `path` as
+/// it goes and carrying on from it if it's already there.
+fn run_checkpointed(options: &Options, path: &str) {
+    let side_size = options.sizes.start;
+    let mut checkpoint = match fs::read_to_string(path) {
+        Ok(text) => {
+            let checkpoint: Checkpoint = text.parse().unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+            if checkpoint.side_size() != side_size {
+                exit_with_error(format!("{}: the checkpoint is for size {}, not {}", path, checkpoint.side_size(), side_size));
+            }
+            if checkpoint.units_done() > 0 {
+                eprintln!("carrying on from {}: {} of {} work units done", path, checkpoint.units_done(), checkpoint.units());
+            }
+            checkpoint
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Checkpoint::new(side_size, CHECKPOINT_DEPTH),
+        Err(e) => exit_with_error(format!("{}: {}", path, e)),
+    };
+
+    let mut last_save = Instant::now();
+    let num_boards = checkpoint.run(|checkpoint| {
+        // The last unit is always saved, so that a finished count never has to be run again.
+        if checkpoint.count().is_none() && last_save.elapsed() < Duration::from_secs(1) {
+            return Ok(());
+        }
+        last_save = Instant::now();
+        save_checkpoint(path, checkpoint)
+    }).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+    writeln!(io::stdout().lock(), "{} {}", side_size, num_boards).expect("failed to write to stdout");
+}
+
+/// Writes `checkpoint` to `path` by way of a temporary file, so that stopping part way through
+/// never leaves a half-written checkpoint behind.
+fn save_checkpoint(path: &str, checkpoint: &Checkpoint) -> io::Result<()> {
+    let temp = format!("{}.tmp", path);
+    fs::write(&temp, checkpoint.to_string())?;
+    fs::rename(&temp, path)
+}
+
+fn run_plain(options: &Options) {
+    if options.binary {
+        return run_binary(options);
+    }
+    let stdout = io::stdout();
+    let out: Box<dyn Write> = match &options.output {
+        Some(path) => Box::new(fs::File::create(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)))),
+        None => Box::new(stdout.lock()),
+    };
+    let mut out = io::BufWriter::new(out);
+    if options.format != Format::Grid {
+        return run_formatted(options, out);
+    }
+
+    for side_size in options.sizes.iter() {
+        let start_time = Instant::now();
+        let height = options.height(side_size);
+        let size = size_name(side_size, height);
+
+        if options.first_only {
+            match first_board(options, side_size) {
+                Some(board) => writeln!(out, "first board of size {}:\n{}", size, board.get_board_string()),
+                None => writeln!(out, "no boards of size {}\n", size),
+            }.and_then(|()| out.flush()).unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+            continue;
+        }
+
+        let mut num_boards = 0;
+        if options.fundamental {
+            for (i, solution) in Board::fundamental_solutions(side_size).enumerate() {
+                writeln!(
+                    out,
+                    "fundamental board #{} of size {} ({} boards in its orbit):\n{}",
+                    i + 1, side_size, solution.orbit_size, solution.board.get_board_string(),
+                ).unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+                num_boards += 1;
+            }
+        } else {
+            for_each_board(options, side_size, &mut |board| {
+                writeln!(out, "board #{} of size {}:\n{}", num_boards + 1, size, board.get_board_string())
+                    .unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+                num_boards += 1;
+            });
+        }
+        let board_find_time = start_time.elapsed();
+
+        let kind = if options.fundamental { "fundamental boards" } else { "boards" };
+        writeln!(out, "found {} {} of size {} in {:?}\n", num_boards, kind, size, board_find_time)
+            .and_then(|()| out.flush())
+            .unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+    }
+}
+
+/// Writes the boards in a format other than the grid, with nothing else in between.
+fn run_formatted(options: &Options, out: impl Write) {
+    let mut writer = SolutionWriter::new(out, options.format)
+        .unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+    for side_size in options.sizes.iter() {
+        for_each_output_board(options, side_size, &mut |board| {
+            writer.write(board).unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+        });
+        writer.flush().unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+    }
+}
+
+/// Writes the boards of the single size asked for to the `--output` file in the binary format.
+fn run_binary(options: &Options) {
+    let path = options.output.as_deref().expect("`--format binary` without `--output`");
+    let side_size = options.sizes.start;
+    let height = options.height(side_size);
+    if height != side_size {
+        exit_with_error(format!("`--format binary` needs a square board, not {}", size_name(side_size, height)));
+    }
+    let file = fs::File::create(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+    let mut writer = BinaryWriter::new(io::BufWriter::new(file), side_size, options.geometry())
+        .unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+    for_each_output_board(options, side_size, &mut |board| {
+        writer.write(board).unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+    });
+    writer.finish().unwrap_or_else(|e| exit_with_error(format!("failed to write boards: {}", e)));
+}
+
+/// Calls `f` with every board of width `side_size` to write out: the first only, the fundamental
+/// ones or all of them, as the options ask.
+fn for_each_output_board(options: &Options, side_size: usize, f: &mut dyn FnMut(&Board)) {
+    if options.first_only {
+        first_board(options, side_size).iter().for_each(f);
+    } else if options.fundamental {
+        Board::fundamental_solutions(side_size).for_each(|solution| f(&solution.board));
+    } else {
+        for_each_board(options, side_size, f);
+    }
+}
+
+/// The first board of width `side_size` the options ask for.
+fn first_board(options: &Options, side_size: usize) -> Option<Board> {
+    if options.has_layout() {
+        options.base_board(side_size).completions().unwrap_or_else(|e| exit_with_error(e)).next()
+    } else if options.has_shape() {
+        Board::placements(side_size, options.height(side_size), options.queens(side_size)).next()
+    } else if options.no_three_in_line {
+        nqueens::first_no_three_in_line_solution(side_size)
+    } else if options.fundamental {
+        Board::fundamental_solutions(side_size).next().map(|s| s.board)
+    } else {
+        nqueens::first_solution_with(side_size, options.backend)
+    }
+}
+
+/// Calls `f` with every board of width `side_size` the options ask for, in order, apart from
+/// fundamental ones.
+fn for_each_board(options: &Options, side_size: usize, f: &mut dyn FnMut(&Board)) {
+    if options.no_three_in_line {
+        nqueens::for_each_no_three_in_line_solution(side_size, f);
+        return;
+    }
+    let solutions = if options.has_layout() {
+        options.base_board(side_size).completions().unwrap_or_else(|e| exit_with_error(e))
+    } else if options.has_shape() {
+        Board::placements(side_size, options.height(side_size), options.queens(side_size))
+    } else {
+        Board::solutions(side_size)
+    };
+    solutions.for_each(|board| f(&board));
+}
+
+fn run_validate(validate: &Validate) {
+    let path = &validate.path;
+    let text = if path == "-" {
+        io::read_to_string(io::stdin()).unwrap_or_else(|e| exit_with_error(format!("stdin: {}", e)))
+    } else {
+        fs::read_to_string(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)))
+    };
+    let board: Board = text.parse().unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+    let geometry = if validate.toroidal { Geometry::Toroidal } else { Geometry::Flat };
+    let board = board.with_geometry(geometry)
+        .unwrap_or_else(|| exit_with_error(format!("{}: `--toroidal` needs a square board", path)));
+
+    let conflicts = board.conflicts();
+    let mut lines: Vec<String> = board.get_board_string().lines().map(str::to_string).collect();
+    for queen in conflicts.iter().flat_map(|conflict| conflict.queens()) {
+        lines[queen.y].replace_range(2 * queen.x..2 * queen.x + 2, "XX");
+    }
+    let mut out = io::stdout().lock();
+    for line in &lines {
+        writeln!(out, "{}", line).expect("failed to write to stdout");
+    }
+    if conflicts.is_empty() {
+        writeln!(out, "\nthe board is valid").expect("failed to write to stdout");
+        return;
+    }
+    let plural = if conflicts.len() == 1 { "" } else { "s" };
+    writeln!(out, "\nthe board isn't valid, {} conflict{}:", conflicts.len(), plural).expect("failed to write to stdout");
+    for conflict in &conflicts {
+        writeln!(out, "  {}", conflict).expect("failed to write to stdout");
+    }
+    drop(out);
+    process::exit(1);
+}
+
+fn run_convert(convert: &Convert) {
+    let path = &convert.input;
+    let read_error = |e: io::Error| -> Board { exit_with_error(format!("{}: {}", path, e)) };
+    let stdin_bytes;
+    let (boards, binary_input): (Box<dyn Iterator<Item=Board>>, bool) = if path == "-" {
+        let mut bytes = vec![];
+        io::stdin().read_to_end(&mut bytes).unwrap_or_else(|e| exit_with_error(format!("stdin: {}", e)));
+        stdin_bytes = bytes;
+        match BinaryReader::new(io::Cursor::new(&stdin_bytes[..])) {
+            Ok(reader) => (Box::new(reader.map(|board| board.unwrap_or_else(read_error))), true),
+            Err(e) => (Box::new(text_boards(convert, &stdin_bytes, e).into_iter()), false),
+        }
+    } else {
+        let file = fs::File::open(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+        match BinaryReader::new(io::BufReader::new(file)) {
+            Ok(reader) => (Box::new(reader.map(|board| board.unwrap_or_else(read_error))), true),
+            Err(e) if e.kind() == io::ErrorKind::InvalidData => {
+                let bytes = fs::read(path).unwrap_or_else(|e| exit_with_error(format!("{}: {}", path, e)));
+                (Box::new(text_boards(convert, &bytes, e).into_iter()), false)
+            }
+            Err(e) => exit_with_error(format!("{}: {}", path, e)),
+        }
+    };
+
+    let output = &convert.output;
+    let write_error = |e: io::Error| format!("{}: {}", output, e);
+    let default_format = if binary_input { Some(Format::Permutation) } else { None };
+    match convert.format.unwrap_or(default_format) {
+        Some(format) => {
+            let stdout = io::stdout();
+            let out: Box<dyn Write> = match &**output {
+                "-" => Box::new(stdout.lock()),
+                _ => Box::new(fs::File::create(output).unwrap_or_else(|e| exit_with_error(write_error(e)))),
+            };
+            let mut writer = SolutionWriter::new(io::BufWriter::new(out), format).unwrap_or_else(|e| exit_with_error(write_error(e)));
+            for board in boards {
+                writer.write(&board).unwrap_or_else(|e| exit_with_error(write_error(e)));
+            }
+            writer.finish().unwrap_or_else(|e| exit_with_error(write_error(e)));
+        }
+        None => {
+            if output == "-" {
+                exit_with_error("binary files can't be written to stdout, as their header is filled in last");
+            }
+            let mut boards = boards.peekable();
+            let first = boards.peek().unwrap_or_else(|| exit_with_error(format!("{}: no boards to convert", path)));
+            let file = fs::File::create(output).unwrap_or_else(|e| exit_with_error(write_error(e)));
+            let mut writer = BinaryWriter::new(io::BufWriter::new(file), first.width(), first.geometry())
+                .unwrap_or_else(|e| exit_with_error(write_error(e)));
+            for board in boards {
+                writer.write(&board).unwrap_or_else(|e| exit_with_error(write_error(e)));
+            }
+            writer.finish().unwrap_or_else(|e| exit_with_error(write_error(e)));
+        }
+    }
+}
+
+/// Parses the boards in the text `bytes` read from the `convert` input, or reports
+/// `binary_error`, why they couldn't be read as a binary file, if they start like one.
+fn text_boards(convert: &Convert, bytes: &[u8], binary_error: io::Error) -> Vec<Board> {
+    let path = &convert.input;
+    if bytes.starts_with(b"NQB1") {
+        exit_with_error(format!("{}: {}", path, binary_error));
+    }
+    let text = std::str::from_utf8(bytes)
+        .unwrap_or_else(|_| exit_with_error(format!("{}: neither a binary solution file nor text", path)));
+    let geometry = if convert.toroidal { Geometry::Toroidal } else { Geometry::Flat };
+    split_boards(text).iter().enumerate().map(|(i, board)| {
+        let board: Board = board.parse().unwrap_or_else(|e| exit_with_error(format!("{}: board #{}: {}", path, i + 1, e)));
+        board.with_geometry(geometry)
+            .unwrap_or_else(|| exit_with_error(format!("{}: board #{}: `--toroidal` needs a square board", path, i + 1)))
+    }).collect()
+}
+
+/// Splits text into the boards it holds: every line in permutation or FEN-like notation on its
+/// own, and runs of other lines, such as grids, up to the next empty line.
+fn split_boards(text: &str) -> Vec<String> {
+    let mut boards = vec![];
+    let mut grid: Vec<&str> = vec![];
+    for line in text.lines() {
+        let line = line.trim_end();
+        let notation = line.contains('/') || line.chars().all(|c| c.is_ascii_digit() || c.is_whitespace() || c == ',');
+        if !grid.is_empty() && (line.is_empty() || notation) {
+            boards.push(grid.join("\n"));
+            grid.clear();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        if notation {
+            boards.push(line.to_string());
+        } else {
+            grid.push(line);
+        }
+    }
+    if !grid.is_empty() {
+        boards.push(grid.join("\n"));
+    }
+    boards
+}
+
+fn run_tui(options: &Options) {
     let completed_board_arc = Arc::new((Mutex::new(None), Condvar::new()));
     let completed_board_arc_cloned = completed_board_arc.clone();
+    let exit_message = if options.sizes.is_finite() { "exit early" } else { "exit" };
     thread::spawn(move|| {
         let (mutex, cvar) = &*completed_board_arc_cloned;
         let mut old_board = None;
@@ -18,7 +963,7 @@ fn main() {
             } = {
                 let completed_board_lock = mutex.lock().unwrap();
                 let lock = cvar.wait_while(completed_board_lock, |b| *b == old_board).unwrap();
-                lock.clone().take().expect("condvar set without board")
+                lock.clone().expect("condvar set without board")
             };
 
             let mut string = String::new();
@@ -27,21 +972,20 @@ fn main() {
             let crossterm_move_to = cursor::MoveTo(0, 0);
             let crossterm_hide = cursor::Hide;
             string += &format!("{}{}{}", crossterm_clear, crossterm_move_to, crossterm_hide);
-            string += &format!("complete board #{} of size {} found\n", board_num, board.side_size);
+            let size = size_name(board.width(), board.height());
+            string += &format!("complete board #{} of size {} found\n", board_num, size);
             string += &board.get_board_string();
-            string += "\nPress Ctrl+C to exit\n";
+            string += &format!("\nPress Ctrl+C to {}\n", exit_message);
 
             if let Some(time) = board_find_time {
-                string += &format!("finding all valid boards of size {} took {:?}", board.side_size, time);
+                string += &format!("finding all valid boards of size {} took {:?}", size, time);
             }
             println!("{}", string);
             old_board = Some(BoardPrint { board, board_num, board_find_time });
         }
     });
-    thread::sleep_ms(50);
-    for side_size in 4.. {
-        let base_board = Board::new(side_size);
-        let num_boards = AtomicUsize::new(0);
+    thread::sleep(Duration::from_millis(50));
+    for side_size in options.sizes.iter() {
         let start_time = Instant::now();
         {
             let mut lock = completed_board_arc.0.lock().unwrap();
@@ -49,47 +993,49 @@ fn main() {
                 b.board_num = 0;
             }
         }
-        find_valid_boards(&base_board, 0, &num_boards, &completed_board_arc);
+        let num_boards = AtomicUsize::new(0);
+        let report = |board: &Board| {
+            let board_num = 1 + num_boards.fetch_add(1, Ordering::SeqCst);
+            if let Ok(mut lock) = completed_board_arc.0.try_lock() {
+                if board_num > lock.as_ref().map(|b| b.board_num).unwrap_or(0) {
+                    *lock = Some(BoardPrint {
+                        board: board.clone(),
+                        board_num,
+                        board_find_time: None,
+                    });
+                    completed_board_arc.1.notify_all();
+                }
+            }
+            thread::yield_now();
+        };
+        if options.has_layout() {
+            let completions = options.base_board(side_size).par_completions().unwrap_or_else(|e| {
+                print!("{}", cursor::Show);
+                exit_with_error(e)
+            });
+            completions.for_each(|board| report(&board));
+        } else if options.has_shape() {
+            let height = options.height(side_size);
+            Board::par_placements(side_size, height, options.queens(side_size)).for_each(|board| report(&board));
+        } else if options.no_three_in_line {
+            nqueens::for_each_no_three_in_line_solution(side_size, report);
+        } else {
+            nqueens::for_each_solution_with(side_size, options.backend, report);
+        }
         let end_time = Instant::now();
         {
             let mut lock = completed_board_arc.0.lock().unwrap();
-            lock.as_mut().unwrap().board_find_time = Some(end_time - start_time);
+            if let Some(b) = lock.as_mut() {
+                b.board_find_time = Some(end_time - start_time);
+            }
         }
         // wait for one and a half seconds
         for _ in 0..50 {
-            thread::sleep_ms(30);
+            thread::sleep(Duration::from_millis(30));
             completed_board_arc.1.notify_all();
         }
     }
-}
-
-fn find_valid_boards(
-    base_board: &Board,
-    col: usize,
-    num_boards: &AtomicUsize,
-    completed_board_arc: &Arc<(Mutex<Option<BoardPrint>>, Condvar)>,
-) {
-    if base_board.is_complete() {
-        let board_num = 1 + num_boards.fetch_add(1, Ordering::SeqCst);
-        if let Ok(mut lock) = completed_board_arc.0.try_lock() {
-            if board_num > lock.as_ref().map(|b| b.board_num).unwrap_or(0) {
-                *lock = Some(BoardPrint {
-                    board: base_board.clone(),
-                    board_num,
-                    board_find_time: None,
-                });
-                completed_board_arc.1.notify_all();
-            }
-        }
-        thread::yield_now();
-        return;
-    }
-
-    base_board.parallel_valid_direct_children_with_queen_in_col(col)
-        .for_each(|child_board| find_valid_boards(&child_board, col + 1, num_boards, completed_board_arc));
-    // for child_board in base_board.valid_direct_children_with_queen_in_col(col) {
-    //     find_valid_boards(&child_board, col + 1, num_boards, completed_board_arc);
-    // }
+    print!("{}", cursor::Show);
 }
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
@@ -99,204 +1045,217 @@ struct BoardPrint {
     board_find_time: Option<Duration>,
 }
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
-struct Queen {
-    x: usize,
-    y: usize,
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
-struct Board {
-    queens: Vec<Queen>,
-    side_size: usize,
-}
+    fn parse(args: &[&str]) -> Result<Options, ArgsError> {
+        Options::parse(args.iter().map(|a| a.to_string()))
+    }
 
-impl Board {
-    fn new(side_size: usize) -> Board {
-        Board {
-            queens: vec![],
-            side_size,
-        }
+    fn parse_command(args: &[&str]) -> Result<Command, ArgsError> {
+        Command::parse(args.iter().map(|a| a.to_string()))
     }
 
-    fn get_board_string(&self) -> String {
-        let mut string = String::new();
-        for y in 0..self.side_size {
-            for x in 0..self.side_size {
-                if self.queens.contains(&Queen::new(x, y)) {
-                    string += "QQ";
-                } else {
-                    string += "__";
-                }
-            }
-            string += "\n";
-        }
-        string
+    #[test]
+    fn test_parse_command() {
+        let count = parse_command(&["count", "8..=10"]).unwrap();
+        assert!(matches!(count, Command::Count(_)));
+        assert_eq!(count.options().unwrap().sizes, Sizes { start: 8, end: Some(10) });
+        assert_eq!(parse_command(&["--count-only", "8..=10"]), Ok(count));
+
+        assert!(matches!(parse_command(&["12"]), Ok(Command::Solve(_))));
+        assert!(parse_command(&["count", "--first-only"]).is_err());
+        assert!(parse_command(&["12", "count"]).is_err());
     }
 
-    fn is_complete(&self) -> bool {
-        self.queens.len() == self.side_size
+    #[test]
+    fn test_parse_validate() {
+        let command = parse_command(&["validate", "--toroidal", "board.txt"]).unwrap();
+        assert_eq!(command, Command::Validate(Validate { path: "board.txt".to_string(), toroidal: true }));
+        assert!(command.options().is_none());
+        assert_eq!(
+            parse_command(&["validate", "-"]),
+            Ok(Command::Validate(Validate { path: "-".to_string(), toroidal: false })),
+        );
+        assert_eq!(parse_command(&["validate", "--help"]), Err(ArgsError::Help));
+        assert!(parse_command(&["validate"]).is_err());
+        assert!(parse_command(&["validate", "a.txt", "b.txt"]).is_err());
+        assert!(parse_command(&["validate", "--count-only", "a.txt"]).is_err());
     }
 
-    fn valid_direct_children_with_queen_in_col(&self, col: usize) -> impl '_ + Iterator<Item=Board> {
-        (0..self.side_size)
-            .map(move |row| Queen::new(col, row))
-            .filter_map(move |queen| self.try_insert_queen(queen))
+    #[test]
+    fn test_parse_convert() {
+        let command = parse_command(&["convert", "--format", "jsonl", "boards.nqb", "-"]).unwrap();
+        assert_eq!(command, Command::Convert(Convert {
+            input: "boards.nqb".to_string(),
+            output: "-".to_string(),
+            format: Some(Some(Format::JsonLines)),
+            toroidal: false,
+        }));
+        assert!(command.options().is_none());
+        let command = parse_command(&["convert", "--toroidal", "-", "boards.nqb", "--format", "binary"]).unwrap();
+        assert_eq!(command, Command::Convert(Convert {
+            input: "-".to_string(),
+            output: "boards.nqb".to_string(),
+            format: Some(None),
+            toroidal: true,
+        }));
+        assert!(matches!(parse_command(&["convert", "a", "b"]), Ok(Command::Convert(Convert { format: None, .. }))));
+        assert!(parse_command(&["convert", "a"]).is_err());
+        assert!(parse_command(&["convert", "a", "b", "c"]).is_err());
+        assert!(parse_command(&["convert", "--format", "yaml", "a", "b"]).is_err());
+        assert!(parse_command(&["convert", "--first-only", "a", "b"]).is_err());
     }
 
-    fn parallel_valid_direct_children_with_queen_in_col(&self, col: usize) -> impl '_ + ParallelIterator<Item=Board> {
-        (0..self.side_size).into_par_iter()
-            .map(move |row| Queen::new(col, row))
-            .filter_map(move |queen| self.try_insert_queen(queen))
+    #[test]
+    fn test_parse_checkpoint() {
+        let command = parse_command(&["count", "--checkpoint", "20.ckpt", "-j", "8", "20"]).unwrap();
+        assert_eq!(command.options().unwrap().checkpoint.as_deref(), Some("20.ckpt"));
+        assert!(parse_command(&["--count-only", "--checkpoint", "20.ckpt", "20"]).is_ok());
+        assert!(parse_command(&["--checkpoint", "20.ckpt", "20"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint", "20.ckpt", "18..=20"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint", "20.ckpt"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint", "20.ckpt", "--fundamental", "20"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint", "20.ckpt", "--toroidal", "20"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint", "20.ckpt", "--backend", "wide", "20"]).is_err());
+        assert!(parse_command(&["count", "--checkpoint"]).is_err());
     }
 
-    fn try_insert_queen(&self, queen: Queen) -> Option<Board> {
-        assert!(queen.x < self.side_size);
-        assert!(queen.y < self.side_size);
+    #[test]
+    fn test_split_boards() {
+        let text = "2413\n3142\n\n__QQ\nQQ__\n\n1/1\n__QQ\nQQ__\n15863724\n";
+        assert_eq!(split_boards(text), vec!["2413", "3142", "__QQ\nQQ__", "1/1", "__QQ\nQQ__", "15863724"]);
+        assert!(split_boards("\n\n").is_empty());
+    }
 
-        for q in &self.queens {
-            if *q == queen {
-                return None;
-            }
-        }
+    #[test]
+    fn test_parse_sizes() {
+        assert_eq!(Sizes::parse("12"), Ok(Sizes { start: 12, end: Some(12) }));
+        assert_eq!(Sizes::parse("8..14"), Ok(Sizes { start: 8, end: Some(13) }));
+        assert_eq!(Sizes::parse("8..=14"), Ok(Sizes { start: 8, end: Some(14) }));
+        assert_eq!(Sizes::parse("4.."), Ok(Sizes { start: 4, end: None }));
+        assert!(Sizes::parse("8..8").is_err());
+        assert!(Sizes::parse("9..=8").is_err());
+        assert!(Sizes::parse("eight").is_err());
 
-        let mut new_board = self.clone();
-        new_board.queens.push(queen);
-        new_board.queens.sort();
-        if new_board.is_valid() {
-            Some(new_board)
-        } else {
-            None
-        }
+        let sizes: Vec<_> = Sizes::parse("8..=10").unwrap().iter().collect();
+        assert_eq!(sizes, vec![8, 9, 10]);
     }
 
-    fn is_valid(&self) -> bool {
-        use std::cell::RefCell;
-        thread_local!{
-            static BOOL_FIELD: RefCell<Vec<bool>> = RefCell::new(Vec::new());
-        }
-        BOOL_FIELD.with(|bool_field| {
-            let mut bool_field = bool_field.borrow_mut();
-            let needed_size = self.side_size * 6;
-            if bool_field.len() < needed_size {
-                *bool_field = vec![false; needed_size];
-            } else {
-                for b in &mut *bool_field {
-                    *b = false;
-                }
-            }
-            let mut bool_field_slice = &mut bool_field[..];
-            let (s, r) = bool_field_slice.split_at_mut(self.side_size);
-            bool_field_slice = r;
-            let occupied_rows = s;
-            let (s, r) = bool_field_slice.split_at_mut(self.side_size);
-            bool_field_slice = r;
-            let occupied_cols = s;
-            let (s, r) = bool_field_slice.split_at_mut(self.side_size * 2);
-            bool_field_slice = r;
-            let occupied_sw_diagonals = s;
-            let (s, r) = bool_field_slice.split_at_mut(self.side_size * 2);
-            bool_field_slice = r;
-            let occupied_se_diagonals = s;
-
-            for q in &self.queens {
-                let row = q.row();
-                let col = q.col();
-                let sw_diagonal = q.sw_diagonal(self.side_size);
-                let se_diagonal = q.se_diagonal(self.side_size);
-
-                if occupied_rows[row] {
-                    return false;
-                } else {
-                    occupied_rows[row] = true;
-                }
-                if occupied_cols[col] {
-                    return false;
-                } else {
-                    occupied_cols[col] = true;
-                }
-                if occupied_sw_diagonals[sw_diagonal] {
-                    return false;
-                } else {
-                    occupied_sw_diagonals[sw_diagonal] = true;
-                }
-                if occupied_se_diagonals[se_diagonal] {
-                    return false;
-                } else {
-                    occupied_se_diagonals[se_diagonal] = true;
-                }
-            }
+    #[test]
+    fn test_parse_options() {
+        let options = parse(&["--count-only", "-j", "3", "8..=10"]).unwrap();
+        assert_eq!(options.sizes, Sizes { start: 8, end: Some(10) });
+        assert!(options.count_only);
+        assert!(!options.first_only);
+        assert_eq!(options.threads, Some(3));
 
-            return true;
-        })
+        assert_eq!(parse(&[]).unwrap().sizes, Sizes { start: 4, end: None });
+        assert_eq!(parse(&["--help"]), Err(ArgsError::Help));
+        assert!(parse(&["--threads"]).is_err());
+        assert!(parse(&["--threads", "0"]).is_err());
+        assert!(parse(&["--frobnicate"]).is_err());
+        assert!(parse(&["8", "9"]).is_err());
+        assert!(parse(&["--count-only", "--first-only"]).is_err());
+        assert_eq!(parse(&["--backend", "wide"]).unwrap().backend, Backend::BitsWide);
+        assert!(parse(&["--backend", "abacus"]).is_err());
+        assert!(parse(&["--fundamental"]).unwrap().fundamental);
     }
-}
 
-impl Queen {
-    fn new(x: usize, y: usize) -> Queen {
-        Queen{ x, y }
-    }
-    fn row(&self) -> usize {
-        self.y
-    }
+    #[test]
+    fn test_parse_layout() {
+        let options = parse(&["--block", "1,2", "--block", "0, 3", "4..=6"]).unwrap();
+        assert_eq!(options.blocked, vec![Square::new(1, 2), Square::new(0, 3)]);
+        assert!(options.has_layout());
+        assert_eq!(options.base_board(5).blocked_squares(), &[Square::new(0, 3), Square::new(1, 2)]);
+        assert!(!parse(&["8"]).unwrap().has_layout());
+
+        assert!(parse(&["--block", "1"]).is_err());
+        assert!(parse(&["--block", "1,a"]).is_err());
+        assert!(parse(&["--block", "4,0", "4..=6"]).is_err());
+        assert!(parse(&["--block", "1,1", "--fundamental"]).is_err());
+        assert!(parse(&["--block", "1,1", "--backend", "bits64"]).is_err());
 
-    fn col(&self) -> usize {
-        self.x
+        let options = parse(&["--mask", "holes.txt", "--count-only"]).unwrap();
+        assert_eq!(options.mask.as_deref(), Some("holes.txt"));
+        assert!(options.has_layout());
+        assert!(parse(&["--mask", "holes.txt", "8"]).is_err());
+        assert!(parse(&["--mask"]).is_err());
     }
 
-    fn sw_diagonal(&self, board_side_size: usize) -> usize {
-        board_side_size + self.x - self.y - 1
+    #[test]
+    fn test_parse_table() {
+        let command = parse_command(&["count", "--table", "--height", "5", "8"]).unwrap();
+        assert!(command.options().unwrap().table);
+        assert!(parse_command(&["--count-only", "--table"]).is_ok());
+        assert!(parse_command(&["--table", "8"]).is_err());
+        assert!(parse_command(&["count", "--table", "--queens", "3"]).is_err());
+        assert!(parse_command(&["count", "--table", "--toroidal"]).is_err());
+        assert!(parse_command(&["count", "--table", "--backend", "bits64"]).is_err());
     }
 
-    fn se_diagonal(&self, board_side_size: usize) -> usize {
-        self.x + self.y
-        // board_side_size + self.y - self.x - 1
+    #[test]
+    fn test_parse_shape() {
+        let options = parse(&["--height", "5", "--queens", "3", "8"]).unwrap();
+        assert!(options.has_shape());
+        assert_eq!(options.height(8), 5);
+        assert_eq!(options.queens(8), 3);
+
+        let options = parse(&["--height", "5", "--block", "7,4", "8..=9"]).unwrap();
+        assert_eq!(options.queens(8), 5);
+        assert_eq!((options.base_board(9).width(), options.base_board(9).height()), (9, 5));
+        assert!(parse(&["--height", "5", "--block", "1,5", "8"]).is_err());
+
+        assert_eq!(parse(&["8"]).unwrap().queens(8), 8);
+        assert!(parse(&["--height"]).is_err());
+        assert!(parse(&["--queens", "many"]).is_err());
+        assert!(parse(&["--height", "5", "--fundamental"]).is_err());
+        assert!(parse(&["--queens", "5", "--backend", "wide"]).is_err());
+        assert!(parse(&["--queens", "5", "--block", "1,1"]).is_err());
+        assert!(parse(&["--height", "5", "--mask", "holes.txt"]).is_err());
+
+        let options = parse(&["--toroidal", "--block", "2,3", "5..=7"]).unwrap();
+        assert!(options.has_layout());
+        assert_eq!(options.base_board(7).geometry(), Geometry::Toroidal);
+        assert!(parse(&["--toroidal", "--height", "5"]).is_err());
+        assert!(parse(&["--toroidal", "--queens", "5"]).is_err());
+        assert!(parse(&["--toroidal", "--fundamental"]).is_err());
+        assert_eq!(size_name(8, 8), "8");
+
+        assert!(parse(&["--no-three-in-line", "--count-only", "8..=12"]).unwrap().no_three_in_line);
+        assert!(parse(&["--no-three-in-line", "--height", "5"]).is_err());
+        assert!(parse(&["--no-three-in-line", "--toroidal"]).is_err());
+        assert!(parse(&["--no-three-in-line", "--fundamental"]).is_err());
+        assert!(parse(&["--no-three-in-line", "--backend", "bits64"]).is_err());
+        assert!(parse_command(&["count", "--no-three-in-line", "--table"]).is_err());
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
     #[test]
-    fn test_sw_diagonal() {
-        let bs = 8;
-        assert_eq!(Queen::new(0, 1).sw_diagonal(bs), 6);
-        assert_eq!(Queen::new(0, 7).sw_diagonal(bs), 0);
-
-        assert_eq!(Queen::new(0, 0).sw_diagonal(bs), 7);
-        assert_eq!(Queen::new(1, 1).sw_diagonal(bs), 7);
-        assert_eq!(Queen::new(2, 2).sw_diagonal(bs), 7);
-        assert_eq!(Queen::new(1, 0).sw_diagonal(bs), 8);
-        assert_eq!(Queen::new(2, 0).sw_diagonal(bs), 9);
-        assert_eq!(Queen::new(7, 0).sw_diagonal(bs), 14);
-    }
-
-    // #[test]
-    // fn test_se_diagonal() {
-    //     let bs = 8;
-    //     assert_eq!(Queen::new(0, 1).se_diagonal(bs), 8);
-    //     assert_eq!(Queen::new(0, 7).se_diagonal(bs), 14);
-
-    //     assert_eq!(Queen::new(0, 0).se_diagonal(bs), 7);
-    //     assert_eq!(Queen::new(1, 1).se_diagonal(bs), 7);
-    //     assert_eq!(Queen::new(2, 2).se_diagonal(bs), 7);
-    //     assert_eq!(Queen::new(1, 0).se_diagonal(bs), 6);
-    //     assert_eq!(Queen::new(2, 0).se_diagonal(bs), 5);
-    //     assert_eq!(Queen::new(7, 0).se_diagonal(bs), 0);
-    //     assert_eq!(Queen::new(6, 1).se_diagonal(bs), 0);
-    // }
-}
-
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-// [][][][][][][][]
-
-// 01234567
-// 12345678
-// 23456789
+    fn test_parse_output() {
+        let options = parse(&["--format", "jsonl", "-o", "boards.jsonl", "8"]).unwrap();
+        assert_eq!(options.format, Format::JsonLines);
+        assert_eq!(options.output.as_deref(), Some("boards.jsonl"));
+        assert!(options.has_output());
+        assert_eq!(parse(&["--format", "permutation"]).unwrap().format, Format::Permutation);
+        assert_eq!(parse(&["--format", "csv"]).unwrap().format, Format::Csv);
+        assert!(!parse(&["--format", "grid"]).unwrap().has_output());
+
+        assert!(parse(&["--format", "yaml"]).is_err());
+        assert!(parse(&["--format"]).is_err());
+        assert!(parse(&["--output"]).is_err());
+        assert!(parse_command(&["count", "--format", "csv"]).is_err());
+        assert!(parse_command(&["--count-only", "-o", "counts.txt"]).is_err());
 
+        let options = parse(&["--format", "binary", "-o", "boards.nqb", "--toroidal", "7"]).unwrap();
+        assert!(options.binary && options.has_output());
+        assert_eq!(options.format, Format::Grid);
+        assert!(parse(&["--format", "binary", "-o", "boards.nqb", "--mask", "holes.txt"]).is_err());
+        assert!(parse(&["--format", "binary", "-o", "boards.nqb", "--block", "0,0", "6"]).is_err());
+        assert!(parse(&["--format", "binary", "8"]).is_err());
+        assert!(parse(&["--format", "binary", "-o", "boards.nqb", "8..=9"]).is_err());
+        assert!(parse(&["--format", "binary", "-o", "boards.nqb"]).is_err());
+        assert!(parse(&["--format", "binary", "-o", "boards.nqb", "--height", "5", "8"]).is_err());
+        assert_eq!(size_name(8, 5), "8x5");
+    }
+}
@@ -0,0 +1,115 @@
+//! Masks: boards drawn as text, one line per row and one character per square. `.` is an open
+//! square, `#` a blocked one and `Q` a queen. Every row must be as long as the first, but the
+//! board doesn't have to be square. Lines that are empty apart from whitespace are skipped, so a
+//! mask can be spaced out or end in a blank line.
+//!
+//! ```text
+//! ..#.
+//! ....
+//! Q...
+//! ...#
+//! ```
+
+use crate::board::{Board, Queen, Square};
+use std::error::Error;
+use std::fmt;
+
+/// Returned by [`Board::from_mask`] for text that isn't a mask. Lines and columns are counted
+/// from 1, as in a text editor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MaskError {
+    /// A row has a different number of squares than the first one.
+    Ragged { line: usize, len: usize, width: usize },
+    /// A character other than `.`, `#` or `Q`.
+    UnknownSquare { line: usize, col: usize, found: char },
+}
+
+impl fmt::Display for MaskError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            MaskError::Ragged { line, len, width } => write!(
+                f,
+                "line {} has {} squares, but the first row has {}",
+                line, len, width,
+            ),
+            MaskError::UnknownSquare { line, col, found } => write!(
+                f,
+                "line {}, column {}: expected `.`, `#` or `Q`, found `{}`",
+                line, col, found,
+            ),
+        }
+    }
+}
+
+impl Error for MaskError {}
+
+impl Board {
+    /// Reads a board from a mask. The queens don't have to be valid.
+    ///
+    /// ```
+    /// use nqueens::{Board, Queen, Square};
+    ///
+    /// let board = Board::from_mask("..#.\n....\nQ...\n...#\n").unwrap();
+    /// assert_eq!(board.side_size(), 4);
+    /// assert_eq!(board.queens(), &[Queen::new(0, 2)]);
+    /// assert_eq!(board.blocked_squares(), &[Square::new(2, 0), Square::new(3, 3)]);
+    /// ```
+    pub fn from_mask(mask: &str) -> Result<Board, MaskError> {
+        let rows: Vec<(usize, &str)> = mask.lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end()))
+            .filter(|(_, line)| !line.is_empty())
+            .collect();
+        let height = rows.len();
+        let width = rows.first().map_or(0, |(_, row)| row.chars().count());
+
+        let mut queens = vec![];
+        let mut blocked = vec![];
+        for (y, &(line, row)) in rows.iter().enumerate() {
+            let len = row.chars().count();
+            if len != width {
+                return Err(MaskError::Ragged { line, len, width });
+            }
+            for (x, c) in row.chars().enumerate() {
+                match c {
+                    '.' => {}
+                    '#' => blocked.push(Square::new(x, y)),
+                    'Q' => queens.push(Queen::new(x, y)),
+                    found => return Err(MaskError::UnknownSquare { line, col: x + 1, found }),
+                }
+            }
+        }
+
+        let board = Board::rect(width, height).with_added_queens(queens)
+            .and_then(|board| board.with_blocked_squares(blocked))
+            .expect("mask square off the board");
+        Ok(board)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_mask() {
+        let board = Board::from_mask("\n.#.\n\n#Q.\r\n...  \n\n").unwrap();
+        assert_eq!(board.side_size(), 3);
+        assert_eq!(board.queens(), &[Queen::new(1, 1)]);
+        assert_eq!(board.blocked_squares(), &[Square::new(0, 1), Square::new(1, 0)]);
+        assert_eq!(Board::from_mask(""), Ok(Board::new(0)));
+
+        assert_eq!(
+            Board::from_mask("...\n..\n...\n"),
+            Err(MaskError::Ragged { line: 2, len: 2, width: 3 }),
+        );
+
+        let board = Board::from_mask("Q..#.\n..Q..\n").unwrap();
+        assert_eq!((board.width(), board.height()), (5, 2));
+        assert_eq!(board.queens(), &[Queen::new(0, 0), Queen::new(2, 1)]);
+        assert_eq!(
+            Board::from_mask("..\n.x\n"),
+            Err(MaskError::UnknownSquare { line: 2, col: 2, found: 'x' }),
+        );
+    }
+}
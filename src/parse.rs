@@ -0,0 +1,460 @@
+//! Reading boards back from text, in any of three notations:
+//!
+//! - The grid [`Board::get_board_string`] draws, two characters per square: `QQ` for a queen,
+//!   `PP` for a pawn, `##` for a blocked square and `__` for an empty one.
+//! - Permutation notation, as written by [`Board::permutation_string`]: the row of each column's
+//!   queen counted from 1, such as `15863724`. Rows can be separated by spaces or commas, which
+//!   they have to be on boards more than 9 rows tall.
+//! - A notation modelled on the piece placement field of chess FEN, as written by
+//!   [`Board::to_fen`]: the rows from the top down separated by `/`, each giving its squares from
+//!   left to right with `Q` for a queen, `P` for a pawn, `#` for a blocked square and a number
+//!   for a run of empty squares. The first solution of the 8-queens problem is
+//!   `Q7/6Q1/4Q3/7Q/1Q6/3Q4/5Q2/2Q5`.
+//!
+//! Boards parsed with [`str::parse`] can be in any of them: text with a `/` in it is read as FEN,
+//! text made only of digits, spaces and commas as a permutation, a single line of `Q`, `P`, `#`
+//! and digits as a one-rank FEN board, and anything else as a grid. A line like `QQ` made only of
+//! pairs of the same piece is a grid row, so the FEN of a one-row board with no empty squares
+//! only reads back with [`Board::from_fen`].
+//! Lines that are empty apart from whitespace are skipped in grids, so a grid can end in a blank
+//! line.
+
+use crate::board::{Board, Queen, Square};
+use std::error::Error;
+use std::fmt;
+use std::str::FromStr;
+
+/// The most squares a board read from FEN can have. The numbers in FEN runs are written so
+/// compactly that without a limit a few bytes of text could ask for a board too big to hold.
+const MAX_FEN_SQUARES: usize = 1 << 24;
+
+/// Returned for text that isn't a board in the notation being read. Lines, columns and
+/// permutation entries are counted from 1, as in a text editor.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ParseBoardError {
+    /// A grid row or FEN rank has a different number of squares than the first one.
+    BadLength { line: usize, len: usize, width: usize },
+    /// Something that isn't a square in a grid or FEN rank. `col` is the character it starts at.
+    UnknownSquare { line: usize, col: usize, found: String },
+    /// A permutation entry that isn't a number.
+    InvalidNumber { position: usize, found: String },
+    /// A permutation entry names a row that isn't on the board.
+    RowOutOfRange { position: usize, row: usize, height: usize },
+    /// Two permutation entries put their queens on the same row.
+    DuplicateRow { position: usize, row: usize },
+    /// A FEN piece or run of empty squares that would make the board more than 2^24 squares in
+    /// all. `col` is the character it starts at.
+    TooBig { line: usize, col: usize },
+    /// A piece or run of empty squares on a FEN rank after the first that goes past the width
+    /// the first rank set. `col` is the character it starts at.
+    ColumnOutOfRange { line: usize, col: usize, width: usize },
+}
+
+impl fmt::Display for ParseBoardError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseBoardError::BadLength { line, len, width } => write!(
+                f,
+                "line {} has {} squares, but the first row has {}",
+                line, len, width,
+            ),
+            ParseBoardError::UnknownSquare { line, col, found } => write!(
+                f,
+                "line {}, column {}: expected a square, found `{}`",
+                line, col, found,
+            ),
+            ParseBoardError::InvalidNumber { position, found } => write!(
+                f,
+                "entry {}: expected a row number, found `{}`",
+                position, found,
+            ),
+            ParseBoardError::RowOutOfRange { position, row, height } => write!(
+                f,
+                "entry {}: row {} isn't one of the board's rows 1 to {}",
+                position, row, height,
+            ),
+            ParseBoardError::DuplicateRow { position, row } => write!(
+                f,
+                "entry {}: row {} already holds a queen",
+                position, row,
+            ),
+            ParseBoardError::TooBig { line, col } => write!(
+                f,
+                "line {}, column {}: the board would have more than {} squares",
+                line, col, MAX_FEN_SQUARES,
+            ),
+            ParseBoardError::ColumnOutOfRange { line, col, width } => write!(
+                f,
+                "line {}, column {}: goes past the {} squares of the first rank",
+                line, col, width,
+            ),
+        }
+    }
+}
+
+impl Error for ParseBoardError {}
+
+impl FromStr for Board {
+    type Err = ParseBoardError;
+
+    /// Reads a board in whichever notation `s` is written in. See the [module docs](self) for
+    /// how it's told apart.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let board = nqueens::first_solution(6).unwrap();
+    /// assert_eq!(board.get_board_string().parse(), Ok(board.clone()));
+    /// assert_eq!(board.permutation_string().unwrap().parse(), Ok(board.clone()));
+    /// assert_eq!(board.to_fen().parse(), Ok(board));
+    /// ```
+    fn from_str(s: &str) -> Result<Board, ParseBoardError> {
+        if s.contains('/') {
+            Board::from_fen(s)
+        } else if s.chars().any(|c| c.is_ascii_digit())
+            && s.chars().all(|c| c.is_ascii_digit() || c.is_whitespace() || c == ',')
+        {
+            Board::from_permutation(s)
+        } else if is_fen_rank(s) {
+            Board::from_fen(s)
+        } else {
+            Board::from_grid(s)
+        }
+    }
+}
+
+impl Board {
+    /// Reads a board drawn the way [`get_board_string`](Board::get_board_string) draws it. The
+    /// queens don't have to be valid.
+    ///
+    /// ```
+    /// use nqueens::{Board, ParseBoardError, Queen};
+    ///
+    /// let board = Board::from_grid("__QQ__\n______\n##__PP\n").unwrap();
+    /// assert_eq!(board.queens(), &[Queen::new(1, 0)]);
+    /// assert_eq!(Board::from_grid("____\n__\n"), Err(ParseBoardError::BadLength { line: 2, len: 1, width: 2 }));
+    /// ```
+    pub fn from_grid(grid: &str) -> Result<Board, ParseBoardError> {
+        let rows: Vec<(usize, Vec<char>)> = grid.lines()
+            .enumerate()
+            .map(|(i, line)| (i + 1, line.trim_end().chars().collect()))
+            .filter(|(_, row): &(usize, Vec<char>)| !row.is_empty())
+            .collect();
+        let height = rows.len();
+        let width = rows.first().map_or(0, |(_, row)| row.len().div_ceil(2));
+
+        let mut queens = vec![];
+        let mut pawns = vec![];
+        let mut blocked = vec![];
+        for (y, (line, row)) in rows.iter().enumerate() {
+            let line = *line;
+            for (x, square) in row.chunks(2).enumerate() {
+                match square {
+                    ['Q', 'Q'] => queens.push(Queen::new(x, y)),
+                    ['P', 'P'] => pawns.push(Square::new(x, y)),
+                    ['#', '#'] => blocked.push(Square::new(x, y)),
+                    ['_', '_'] => {}
+                    found => {
+                        let found = found.iter().collect();
+                        return Err(ParseBoardError::UnknownSquare { line, col: 2 * x + 1, found });
+                    }
+                }
+            }
+            let len = row.len() / 2;
+            if len != width {
+                return Err(ParseBoardError::BadLength { line, len, width });
+            }
+        }
+        Ok(build(width, height, queens, pawns, blocked))
+    }
+
+    /// Reads a board in permutation notation, which has a queen in every column and on every row.
+    /// The queens don't have to be valid otherwise.
+    ///
+    /// ```
+    /// use nqueens::{Board, ParseBoardError};
+    ///
+    /// assert_eq!(Board::from_permutation("2413"), Ok(Board::from_rows(&[1, 3, 0, 2]).unwrap()));
+    /// assert_eq!(Board::from_permutation("1, 3, 2"), Ok(Board::from_rows(&[0, 2, 1]).unwrap()));
+    /// assert_eq!(Board::from_permutation("2423"), Err(ParseBoardError::DuplicateRow { position: 3, row: 2 }));
+    /// assert_eq!(
+    ///     Board::from_permutation("2415"),
+    ///     Err(ParseBoardError::RowOutOfRange { position: 4, row: 5, height: 4 }),
+    /// );
+    /// ```
+    pub fn from_permutation(permutation: &str) -> Result<Board, ParseBoardError> {
+        let permutation = permutation.trim();
+        let entries: Vec<String> = if permutation.contains(|c: char| c.is_whitespace() || c == ',') {
+            permutation.split(|c: char| c.is_whitespace() || c == ',')
+                .filter(|entry| !entry.is_empty())
+                .map(str::to_string)
+                .collect()
+        } else {
+            permutation.chars().map(String::from).collect()
+        };
+
+        let height = entries.len();
+        let mut taken = vec![false; height];
+        let mut rows = Vec::with_capacity(height);
+        for (i, entry) in entries.into_iter().enumerate() {
+            let position = i + 1;
+            let row: usize = match entry.parse() {
+                Ok(row) => row,
+                Err(_) => return Err(ParseBoardError::InvalidNumber { position, found: entry }),
+            };
+            if row == 0 || row > height {
+                return Err(ParseBoardError::RowOutOfRange { position, row, height });
+            }
+            if taken[row - 1] {
+                return Err(ParseBoardError::DuplicateRow { position, row });
+            }
+            taken[row - 1] = true;
+            rows.push(row - 1);
+        }
+        Ok(Board::from_rows(&rows).expect("permutation row off the board"))
+    }
+
+    /// Reads a board in FEN-like rank notation. The queens don't have to be valid.
+    ///
+    /// ```
+    /// use nqueens::{Board, Queen, Square};
+    ///
+    /// let board = Board::from_fen("1Q2/3#/P3").unwrap();
+    /// assert_eq!((board.width(), board.height()), (4, 3));
+    /// assert_eq!(board.queens(), &[Queen::new(1, 0)]);
+    /// assert_eq!(board.pawns(), &[Square::new(0, 2)]);
+    /// assert_eq!(board.blocked_squares(), &[Square::new(3, 1)]);
+    /// ```
+    pub fn from_fen(fen: &str) -> Result<Board, ParseBoardError> {
+        let fen = fen.trim();
+        if fen.is_empty() {
+            return Ok(Board::new(0));
+        }
+
+        let mut width = None;
+        let mut queens = vec![];
+        let mut pawns = vec![];
+        let mut blocked = vec![];
+        let ranks: Vec<&str> = fen.split('/').collect();
+        if ranks.len() > MAX_FEN_SQUARES {
+            return Err(ParseBoardError::TooBig { line: MAX_FEN_SQUARES + 1, col: 1 });
+        }
+        for (y, rank) in ranks.iter().enumerate() {
+            let line = y + 1;
+            let mut x = 0;
+            let mut chars = rank.trim().char_indices().peekable();
+            let first_width = width;
+            let max_width = first_width.unwrap_or(MAX_FEN_SQUARES / ranks.len());
+            let too_far = |col| match first_width {
+                Some(width) => ParseBoardError::ColumnOutOfRange { line, col, width },
+                None => ParseBoardError::TooBig { line, col },
+            };
+            while let Some((i, c)) = chars.next() {
+                if x >= max_width {
+                    return Err(too_far(i + 1));
+                }
+                match c {
+                    'Q' => queens.push(Queen::new(x, y)),
+                    'P' => pawns.push(Square::new(x, y)),
+                    '#' => blocked.push(Square::new(x, y)),
+                    '1'..='9' => {
+                        let mut run = c.to_digit(10).unwrap() as usize;
+                        while let Some(digit) = chars.peek().and_then(|&(_, c)| c.to_digit(10)) {
+                            run = run.checked_mul(10)
+                                .and_then(|run| run.checked_add(digit as usize))
+                                .filter(|&run| run <= max_width)
+                                .ok_or_else(|| too_far(i + 1))?;
+                            chars.next();
+                        }
+                        x = Some(x + run).filter(|&x| x <= max_width).ok_or_else(|| too_far(i + 1))?;
+                        continue;
+                    }
+                    found => return Err(ParseBoardError::UnknownSquare { line, col: i + 1, found: found.to_string() }),
+                }
+                x += 1;
+            }
+            let width = *width.get_or_insert(x);
+            if x != width {
+                return Err(ParseBoardError::BadLength { line, len: x, width });
+            }
+        }
+        Ok(build(width.unwrap_or(0), ranks.len(), queens, pawns, blocked))
+    }
+
+    /// The board in FEN-like rank notation. A queen on a blocked square is written as a queen.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let board = nqueens::first_solution(8).unwrap();
+    /// assert_eq!(board.to_fen(), "Q7/6Q1/4Q3/7Q/1Q6/3Q4/5Q2/2Q5");
+    /// ```
+    pub fn to_fen(&self) -> String {
+        let mut ranks = vec![];
+        for y in 0..self.height() {
+            let mut rank = String::new();
+            let mut empty = 0;
+            for x in 0..self.width() {
+                let square = Square::new(x, y);
+                let piece = if self.queens().contains(&square) {
+                    'Q'
+                } else if self.has_pawn(square) {
+                    'P'
+                } else if self.is_blocked(square) {
+                    '#'
+                } else {
+                    empty += 1;
+                    continue;
+                };
+                if empty > 0 {
+                    rank += &empty.to_string();
+                    empty = 0;
+                }
+                rank.push(piece);
+            }
+            if empty > 0 {
+                rank += &empty.to_string();
+            }
+            ranks.push(rank);
+        }
+        ranks.join("/")
+    }
+}
+
+/// Whether `s` is a single FEN rank with at least one piece on it, and not a grid row.
+fn is_fen_rank(s: &str) -> bool {
+    let s = s.trim().as_bytes();
+    let is_piece = |c: &u8| b"QP#".contains(c);
+    let grid_row = s.len().is_multiple_of(2) && s.chunks(2).all(|pair| pair[0] == pair[1]);
+    s.iter().any(is_piece) && s.iter().all(|c| is_piece(c) || c.is_ascii_digit()) && !grid_row
+}
+
+fn build(width: usize, height: usize, queens: Vec<Queen>, pawns: Vec<Square>, blocked: Vec<Square>) -> Board {
+    Board::rect(width, height).with_added_queens(queens)
+        .and_then(|board| board.with_pawns(pawns))
+        .and_then(|board| board.with_blocked_squares(blocked))
+        .expect("parsed square off the board")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> Board {
+        Board::rect(5, 3).with_added_queens(vec![Queen::new(0, 0), Queen::new(4, 1)]).unwrap()
+            .with_pawns(vec![Square::new(2, 2)]).unwrap()
+            .with_blocked_squares(vec![Square::new(1, 1), Square::new(3, 0)]).unwrap()
+    }
+
+    #[test]
+    fn test_round_trips() {
+        let board = sample();
+        assert_eq!(board.to_fen(), "Q2#1/1#2Q/2P2");
+        assert_eq!(Board::from_fen(&board.to_fen()), Ok(board.clone()));
+        assert_eq!(Board::from_grid(&board.get_board_string()), Ok(board.clone()));
+        assert_eq!(board.to_fen().parse(), Ok(board.clone()));
+        assert_eq!(board.get_board_string().parse(), Ok(board));
+
+        for board in Board::solutions(11).take(20) {
+            let permutation = board.permutation_string().unwrap();
+            assert!(permutation.contains(' '));
+            assert_eq!(permutation.parse(), Ok(board.clone()));
+            assert_eq!(Board::from_fen(&board.to_fen()), Ok(board));
+        }
+
+        assert_eq!("".parse(), Ok(Board::new(0)));
+        assert_eq!(Board::from_fen(""), Ok(Board::new(0)));
+        assert_eq!(Board::from_permutation(""), Ok(Board::new(0)));
+        assert_eq!(Board::new(0).to_fen(), "");
+    }
+
+    #[test]
+    fn test_one_rank_fen() {
+        for width in 1..=6 {
+            // Every one-row board with a queen, pawn, blocked square or nothing on each square, apart
+            // from the empty ones, which are permutations.
+            for squares in 1..4usize.pow(width as u32) {
+                let kind = |x: usize| squares / 4usize.pow(x as u32) % 4;
+                let on = |k| (0..width).filter(|&x| kind(x) == k).map(|x| Square::new(x, 0)).collect();
+                let board = build(width, 1, on(1), on(2), on(3));
+                let fen = board.to_fen();
+                // With no empty squares and the pieces in pairs, the FEN is also a grid row.
+                let full = (0..width).all(|x| kind(x) != 0);
+                if full && width.is_multiple_of(2) && (0..width).step_by(2).all(|x| kind(x) == kind(x + 1)) {
+                    assert_eq!(fen.parse(), Board::from_grid(&fen), "{}", fen);
+                } else {
+                    assert_eq!(fen.parse(), Ok(board), "{}", fen);
+                }
+            }
+        }
+        assert_eq!("1Q2\n".parse(), Ok(build(4, 1, vec![Queen::new(1, 0)], vec![], vec![])));
+        assert_eq!("Q\n".parse(), Ok(Board::with_queens(1, vec![Queen::new(0, 0)]).unwrap()));
+        assert_eq!("QQ\n".parse(), Ok(Board::with_queens(1, vec![Queen::new(0, 0)]).unwrap()));
+        assert_eq!("QQ2".parse::<Board>().map(|board| board.width()), Ok(4));
+    }
+
+    #[test]
+    fn test_grid_errors() {
+        let board = Board::with_queens(2, vec![Queen::new(1, 1)]).unwrap();
+        assert_eq!(Board::from_grid("\n____\r\n__QQ  \n\n"), Ok(board));
+        assert_eq!(
+            Board::from_grid("____\n______\n"),
+            Err(ParseBoardError::BadLength { line: 2, len: 3, width: 2 }),
+        );
+        assert_eq!(
+            Board::from_grid("____\n_Q__\n"),
+            Err(ParseBoardError::UnknownSquare { line: 2, col: 1, found: "_Q".to_string() }),
+        );
+        assert_eq!(
+            Board::from_grid("____\n___\n"),
+            Err(ParseBoardError::UnknownSquare { line: 2, col: 3, found: "_".to_string() }),
+        );
+    }
+
+    #[test]
+    fn test_permutation_errors() {
+        assert_eq!(
+            Board::from_permutation("12a4"),
+            Err(ParseBoardError::InvalidNumber { position: 3, found: "a".to_string() }),
+        );
+        assert_eq!(
+            Board::from_permutation("1 -2"),
+            Err(ParseBoardError::InvalidNumber { position: 2, found: "-2".to_string() }),
+        );
+        assert_eq!(
+            Board::from_permutation("10 2"),
+            Err(ParseBoardError::RowOutOfRange { position: 1, row: 10, height: 2 }),
+        );
+        assert_eq!(Board::from_permutation("0"), Err(ParseBoardError::RowOutOfRange { position: 1, row: 0, height: 1 }));
+        assert_eq!(Board::from_permutation("3,1,3"), Err(ParseBoardError::DuplicateRow { position: 3, row: 3 }));
+        assert_eq!("3 1 2".parse(), Ok(Board::from_rows(&[2, 0, 1]).unwrap()));
+        assert_eq!("312\n".parse(), Ok(Board::from_rows(&[2, 0, 1]).unwrap()));
+    }
+
+    #[test]
+    fn test_fen_errors() {
+        let board = Board::rect(12, 2).with_added_queens(vec![Queen::new(0, 1)]).unwrap();
+        assert_eq!(Board::from_fen("12/Q11"), Ok(board));
+        assert_eq!(Board::from_fen("3/2Q/2"), Err(ParseBoardError::BadLength { line: 3, len: 2, width: 3 }));
+        assert_eq!(Board::from_fen("3/2Q1"), Err(ParseBoardError::ColumnOutOfRange { line: 2, col: 3, width: 3 }));
+        assert_eq!(
+            Board::from_fen("3/1K1/3"),
+            Err(ParseBoardError::UnknownSquare { line: 2, col: 2, found: "K".to_string() }),
+        );
+
+        // Boards too big to hold are errors rather than overflowing or running out of memory, and
+        // ranks after the first stop at the first piece or run that goes past its width.
+        let run = "1234567890123456789012345";
+        assert_eq!(Board::from_fen(run), Err(ParseBoardError::TooBig { line: 1, col: 1 }));
+        assert_eq!(Board::from_fen("99999999999/99999999999"), Err(ParseBoardError::TooBig { line: 1, col: 1 }));
+        assert_eq!(Board::from_fen("Q8388607/8388608").map(|board| board.height()), Ok(2));
+        assert_eq!(Board::from_fen("QQ8388607/8388609"), Err(ParseBoardError::TooBig { line: 1, col: 3 }));
+        assert_eq!(Board::from_fen(&"/".repeat(MAX_FEN_SQUARES)), Err(ParseBoardError::TooBig { line: MAX_FEN_SQUARES + 1, col: 1 }));
+        let out_of_range = |col| Err(ParseBoardError::ColumnOutOfRange { line: 2, col, width: 4 });
+        assert_eq!(Board::from_fen(&format!("4/Q{}", run)), out_of_range(2));
+        assert_eq!(Board::from_fen("4/12"), out_of_range(1));
+        assert_eq!(Board::from_fen("4/9"), out_of_range(1));
+        assert_eq!(Board::from_fen("4/4Q"), out_of_range(2));
+        assert_eq!(Board::from_fen("4/Q3#"), out_of_range(3));
+    }
+}
@@ -0,0 +1,335 @@
+//! Pawns: pieces that take up a square and block the lines running through it, so two queens on
+//! the same row, column or diagonal only attack each other if there's no pawn between them. With
+//! `k` pawns, `n + k` queens fit on an `n`×`n` board once the board is big enough, and at most
+//! that many ever do, since a row with `p` pawns holds at most `p + 1` queens.
+//!
+//! On toroidal boards every line is a loop, so two queens on it are only safe from each other if
+//! there are pawns between them both ways round.
+
+use crate::board::{Board, Geometry, Queen, Square};
+
+/// The most queens that fit on a board with the help of some pawns, found by
+/// [`Board::max_queens_with_pawns`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct MaxQueens {
+    pub queens: usize,
+    /// Every placement of that many queens, sorted by their queens and then by their pawns.
+    pub boards: Vec<Board>,
+}
+
+/// What lies on a square, for the line-of-sight checks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Piece {
+    Queen,
+    Pawn,
+}
+
+impl Board {
+    /// Whether `a` and `b` lie on a common row, column or diagonal with no pawn between them.
+    /// Blocked squares don't block the line.
+    pub fn is_attacking(&self, a: Queen, b: Queen) -> bool {
+        if a == b {
+            return false;
+        }
+        if self.geometry() == Geometry::Toroidal {
+            return self.is_attacking_around(a, b);
+        }
+        let (dx, dy) = (b.x as isize - a.x as isize, b.y as isize - a.y as isize);
+        if dx != 0 && dy != 0 && dx.abs() != dy.abs() {
+            return false;
+        }
+        let (step_x, step_y) = (dx.signum(), dy.signum());
+        let distance = dx.abs().max(dy.abs());
+        (1..distance).all(|i| {
+            let x = (a.x as isize + i * step_x) as usize;
+            let y = (a.y as isize + i * step_y) as usize;
+            !self.has_pawn(Square::new(x, y))
+        })
+    }
+
+    /// [`is_attacking`](Board::is_attacking) for toroidal boards. Walks from `a` along each line
+    /// through it until reaching `b`, then checks the squares passed on the way and the ones
+    /// left on the way back round.
+    fn is_attacking_around(&self, a: Queen, b: Queen) -> bool {
+        let n = self.side_size();
+        let step = |(dx, dy): (usize, usize), steps: usize| Square::new((a.x + dx * steps) % n, (a.y + dy * steps) % n);
+        let directions = [(1, 0), (0, 1), (1, 1), (1, n - 1)];
+        IntoIterator::into_iter(directions).any(|direction| {
+            match (1..n).find(|&steps| step(direction, steps) == b) {
+                Some(distance) => {
+                    let clear = |steps: std::ops::Range<usize>| steps.map(|i| step(direction, i)).all(|s| !self.has_pawn(s));
+                    clear(1..distance) || clear(distance + 1..n)
+                }
+                None => false,
+            }
+        })
+    }
+
+    /// [`is_valid`](Board::is_valid) for boards with pawns, where a line can hold several queens
+    /// as long as pawns separate them.
+    pub(crate) fn is_valid_with_pawns(&self) -> bool {
+        if self.queens().iter().any(|&q| self.has_pawn(q)) {
+            return false;
+        }
+        let wraps = self.geometry() == Geometry::Toroidal;
+        lines(self).all(|line| {
+            runs(&line, 2, wraps).iter().all(|w| w[0].1 != Piece::Queen || w[1].1 != Piece::Queen)
+        })
+    }
+
+    /// Whether every pawn on the board stands between two queens that would otherwise attack
+    /// each other.
+    fn every_pawn_blocks(&self) -> bool {
+        let wraps = self.geometry() == Geometry::Toroidal;
+        let mut blocking = vec![];
+        for line in lines(self) {
+            for w in runs(&line, 3, wraps) {
+                if let [(_, Piece::Queen), (pawn, Piece::Pawn), (_, Piece::Queen)] = w[..] {
+                    blocking.push(pawn);
+                }
+            }
+        }
+        blocking.sort();
+        blocking.dedup();
+        blocking.len() == self.pawns().len()
+    }
+
+    /// The most queens that fit on a `side_size`×`side_size` board with up to `max_pawns` pawns
+    /// placed anywhere, along with every placement that reaches it. Pawns that don't separate
+    /// two queens are left off, so the placements can have anywhere from none to `max_pawns`
+    /// pawns: on an 8×8 board every pawn makes room for another queen and they all use exactly
+    /// `max_pawns`, but the 4 queens that fit on a 4×4 board with one pawn can do without it.
+    ///
+    /// The search walks the squares row by row, deciding for each whether it gets a queen, a
+    /// pawn or nothing. It starts out asking for `side_size + max_pawns` queens and lowers the
+    /// target until a placement turns up, so it's only practical for small boards.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let max = Board::max_queens_with_pawns(8, 1);
+    /// assert_eq!(max.queens, 9);
+    /// assert!(max.boards.iter().all(|b| b.is_valid() && b.pawns().len() == 1));
+    /// ```
+    pub fn max_queens_with_pawns(side_size: usize, max_pawns: usize) -> MaxQueens {
+        let diagonals = (2 * side_size).saturating_sub(1);
+        for target in (0..=side_size + max_pawns).rev() {
+            let mut search = PawnSearch {
+                side_size,
+                target,
+                pawns_left: max_pawns,
+                row_hot: false,
+                cols_hot: vec![false; side_size],
+                sw_hot: vec![false; diagonals],
+                se_hot: vec![false; diagonals],
+                queens: vec![],
+                pawns: vec![],
+                boards: vec![],
+            };
+            search.search(0);
+            if !search.boards.is_empty() {
+                let mut boards = search.boards;
+                boards.sort_by(|a, b| (a.queens(), a.pawns()).cmp(&(b.queens(), b.pawns())));
+                return MaxQueens { queens: target, boards };
+            }
+        }
+        unreachable!("the empty board always fits zero queens")
+    }
+}
+
+/// Names the line a square of the board lies on in one direction, and the square's position
+/// along it.
+type LineKey = fn(&Board, Square) -> (usize, usize);
+
+/// The queens and pawns on each row, column and diagonal holding at least two pieces, in order
+/// along the line.
+fn lines(board: &Board) -> impl '_ + Iterator<Item=Vec<(Square, Piece)>> {
+    let pieces: Vec<(Square, Piece)> = board.queens().iter().map(|&q| (q, Piece::Queen))
+        .chain(board.pawns().iter().map(|&p| (p, Piece::Pawn)))
+        .collect();
+
+    let directions: [LineKey; 4] = [
+        |_, s| (s.y, s.x),
+        |_, s| (s.x, s.y),
+        |board, s| (board.diagonals(s).0, s.x),
+        |board, s| (board.diagonals(s).1, s.x),
+    ];
+    IntoIterator::into_iter(directions).flat_map(move |direction| {
+        let mut keyed: Vec<_> = pieces.iter().map(|&(s, piece)| (direction(board, s), s, piece)).collect();
+        keyed.sort_by_key(|&(key, _, _)| key);
+        let mut lines = vec![];
+        let mut start = 0;
+        for end in 1..=keyed.len() {
+            if end == keyed.len() || keyed[end].0 .0 != keyed[start].0 .0 {
+                if end - start > 1 {
+                    lines.push(keyed[start..end].iter().map(|&(_, s, piece)| (s, piece)).collect());
+                }
+                start = end;
+            }
+        }
+        lines
+    })
+}
+
+/// The runs of `len` consecutive pieces along `line`. If the line `wraps` around the board, the
+/// runs across its ends count too, as long as the line holds at least `len` pieces.
+fn runs(line: &[(Square, Piece)], len: usize, wraps: bool) -> Vec<Vec<(Square, Piece)>> {
+    let starts = if wraps && line.len() >= len { line.len() } else { (line.len() + 1).saturating_sub(len) };
+    (0..starts)
+        .map(|start| (start..start + len).map(|i| line[i % line.len()]).collect())
+        .collect()
+}
+
+/// A search for placements of exactly `target` queens. A line is hot if the last piece placed on
+/// it is a queen, in which case the next queen on it would be attacked.
+struct PawnSearch {
+    side_size: usize,
+    target: usize,
+    pawns_left: usize,
+    row_hot: bool,
+    cols_hot: Vec<bool>,
+    sw_hot: Vec<bool>,
+    se_hot: Vec<bool>,
+    queens: Vec<Queen>,
+    pawns: Vec<Square>,
+    boards: Vec<Board>,
+}
+
+impl PawnSearch {
+    /// Decides the squares from `cell` onwards, counting row by row from the top-left corner.
+    fn search(&mut self, cell: usize) {
+        if self.queens.len() == self.target {
+            // Any later pawn would need another queen after it to separate.
+            let board = Board::with_queens(self.side_size, self.queens.iter().copied())
+                .and_then(|board| board.with_pawns(self.pawns.iter().copied()))
+                .expect("pawn search placed a piece off the board");
+            if board.every_pawn_blocks() {
+                self.boards.push(board);
+            }
+            return;
+        }
+        if cell == self.side_size * self.side_size {
+            return;
+        }
+
+        let n = self.side_size;
+        let (x, y) = (cell % n, cell / n);
+        let row_hot = if x == 0 { false } else { self.row_hot };
+        // Each row takes at most one queen more than it has pawns.
+        let row_room = if row_hot { 0 } else { 1 };
+        if self.queens.len() + row_room + (n - y - 1) + self.pawns_left < self.target {
+            return;
+        }
+
+        let square = Square::new(x, y);
+        let (sw, se) = (square.sw_diagonal(n), square.se_diagonal(n));
+        let hot = (row_hot, self.cols_hot[x], self.sw_hot[sw], self.se_hot[se]);
+        let set = |search: &mut PawnSearch, (row, col, sw_hot, se_hot): (bool, bool, bool, bool)| {
+            search.row_hot = row;
+            search.cols_hot[x] = col;
+            search.sw_hot[sw] = sw_hot;
+            search.se_hot[se] = se_hot;
+        };
+
+        if hot == (false, false, false, false) {
+            self.queens.push(square);
+            set(self, (true, true, true, true));
+            self.search(cell + 1);
+            self.queens.pop();
+        }
+
+        // A pawn is only worth placing right after a queen on one of its lines.
+        if self.pawns_left > 0 && hot != (false, false, false, false) {
+            self.pawns.push(square);
+            self.pawns_left -= 1;
+            set(self, (false, false, false, false));
+            self.search(cell + 1);
+            self.pawns_left += 1;
+            self.pawns.pop();
+        }
+
+        set(self, hot);
+        self.search(cell + 1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_of_sight() {
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(4, 0), Queen::new(3, 3)])
+            .unwrap()
+            .with_pawns(vec![Square::new(2, 0), Square::new(2, 2)])
+            .unwrap();
+        assert!(!board.is_attacking(Queen::new(0, 0), Queen::new(4, 0)));
+        assert!(!board.is_attacking(Queen::new(0, 0), Queen::new(3, 3)));
+        assert!(board.is_attacking(Queen::new(4, 0), Queen::new(3, 1)));
+        assert!(!board.is_attacking(Queen::new(4, 0), Queen::new(2, 1)));
+        assert!(board.is_valid());
+        assert!(board.every_pawn_blocks());
+
+        assert!(board.try_insert_queen(Queen::new(2, 2)).is_none());
+        assert!(board.try_insert_queen(Queen::new(1, 4)).is_some());
+        assert!(board.try_insert_queen(Queen::new(4, 4)).is_none());
+        assert_eq!(board.get_board_string().lines().next(), Some("QQ__PP__QQ"));
+
+        let unblocked = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(4, 4)]).unwrap()
+            .with_pawns(vec![Square::new(2, 0)])
+            .unwrap();
+        assert!(!unblocked.is_valid());
+        assert!(!unblocked.every_pawn_blocks());
+    }
+
+    #[test]
+    fn test_toroidal_line_of_sight() {
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(3, 0)]).unwrap()
+            .with_pawns(vec![Square::new(1, 0)]).unwrap();
+        assert!(board.is_valid());
+        // Round the other way, nothing stands between the two queens.
+        let torus = board.clone().with_geometry(Geometry::Toroidal).unwrap();
+        assert!(torus.is_attacking(Queen::new(0, 0), Queen::new(3, 0)));
+        assert!(!torus.is_valid());
+
+        let torus = torus.with_pawns(vec![Square::new(4, 0)]).unwrap();
+        assert!(!torus.is_attacking(Queen::new(0, 0), Queen::new(3, 0)));
+        assert!(torus.is_valid());
+        assert!(torus.every_pawn_blocks());
+
+        // (4, 4) and (0, 0) share a wrapped diagonal.
+        assert!(torus.is_attacking(Queen::new(0, 0), Queen::new(4, 4)));
+        assert!(torus.try_insert_queen(Queen::new(4, 4)).is_none());
+        assert!(torus.with_pawns(vec![Square::new(2, 2)]).unwrap().try_insert_queen(Queen::new(4, 4)).is_none());
+    }
+
+    #[test]
+    fn test_max_queens_with_pawns() {
+        let max = Board::max_queens_with_pawns(8, 0);
+        assert_eq!(max.queens, 8);
+        assert_eq!(max.boards, Board::solutions(8).collect::<Vec<_>>());
+
+        // The nine queens with one pawn fall into 16 orbits under the board's symmetries. Each
+        // pawn makes room for one more queen, so the best placements use all of them.
+        for &(pawns, queens, placements) in &[(1, 9, 128), (2, 10, 44)] {
+            let max = Board::max_queens_with_pawns(8, pawns);
+            assert_eq!(max.queens, queens);
+            assert_eq!(max.boards.len(), placements);
+            for board in &max.boards {
+                assert!(board.is_valid());
+                assert_eq!(board.queens().len(), queens);
+                assert_eq!(board.pawns().len(), pawns);
+            }
+        }
+
+        // A pawn doesn't always help, and then it's left off.
+        let max = Board::max_queens_with_pawns(4, 1);
+        assert_eq!((max.queens, max.boards.len()), (4, 10));
+        assert_eq!(max.boards.iter().filter(|b| b.pawns().is_empty()).count(), 2);
+        assert!(max.boards.iter().all(|b| b.is_valid() && b.pawns().len() <= 1));
+        assert_eq!(Board::max_queens_with_pawns(1, 1).boards[0].pawns(), &[]);
+
+        assert_eq!(Board::max_queens_with_pawns(3, 0).queens, 2);
+        assert_eq!(Board::max_queens_with_pawns(0, 2), MaxQueens { queens: 0, boards: vec![Board::new(0)] });
+    }
+}
@@ -0,0 +1,283 @@
+//! Non-attacking placements of other chess pieces: rooks, bishops, kings, knights and the amazon,
+//! which moves like a queen and a knight at once.
+//!
+//! Most of these pieces don't attack along columns, so a column can hold several of them and the
+//! column-by-column search used for queens doesn't apply. This module is a separate solver of its
+//! own: it goes through the squares row by row, deciding for each whether it gets a piece, and
+//! carries along the set of later squares that are still free. Each piece's attacks are worked
+//! out once per square up front, which is all the search needs to know about the piece.
+//!
+//! [`Queen`] implements [`Piece`] too, so queens can be placed this way, but the queen searches
+//! elsewhere in the crate and [`Board::is_valid`](crate::Board::is_valid) don't go through
+//! [`Piece`].
+
+use crate::bitboard::{with_row_set, RowSet};
+use crate::board::{Queen, Square};
+use rayon::prelude::*;
+
+/// A kind of chess piece, described by how it attacks. Attacks go both ways: every piece here
+/// attacks a square exactly when a piece of the same kind on that square attacks it back.
+pub trait Piece {
+    /// The steps the piece slides along, as many times as it likes. Nothing on the board stops
+    /// it, since the pieces never block each other's lines.
+    const SLIDES: &'static [(isize, isize)];
+    /// The offsets the piece jumps to in a single move.
+    const LEAPS: &'static [(isize, isize)];
+
+    /// Whether a piece on `from` attacks `to`.
+    fn attacks(from: Square, to: Square) -> bool {
+        let (dx, dy) = (to.x as isize - from.x as isize, to.y as isize - from.y as isize);
+        if (dx, dy) == (0, 0) {
+            return false;
+        }
+        Self::LEAPS.contains(&(dx, dy)) || Self::SLIDES.iter().any(|&(sx, sy)| {
+            let steps = if sx != 0 { dx / sx } else { dy / sy };
+            steps > 0 && (sx * steps, sy * steps) == (dx, dy)
+        })
+    }
+}
+
+const ORTHOGONAL: &[(isize, isize)] = &[(1, 0), (-1, 0), (0, 1), (0, -1)];
+const DIAGONAL: &[(isize, isize)] = &[(1, 1), (1, -1), (-1, 1), (-1, -1)];
+const ALL_DIRECTIONS: &[(isize, isize)] = &[(1, 0), (-1, 0), (0, 1), (0, -1), (1, 1), (1, -1), (-1, 1), (-1, -1)];
+const KNIGHT_LEAPS: &[(isize, isize)] = &[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)];
+
+/// Attacks along rows and columns.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Rook;
+
+/// Attacks along diagonals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Bishop;
+
+/// Attacks the eight squares around it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct King;
+
+/// Jumps two squares along a row or column and one across.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Knight;
+
+/// Moves like a queen and a knight at once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Amazon;
+
+/// The name the amazon usually goes by in n-queens problems.
+pub type SuperQueen = Amazon;
+
+impl Piece for Rook {
+    const SLIDES: &'static [(isize, isize)] = ORTHOGONAL;
+    const LEAPS: &'static [(isize, isize)] = &[];
+}
+
+impl Piece for Bishop {
+    const SLIDES: &'static [(isize, isize)] = DIAGONAL;
+    const LEAPS: &'static [(isize, isize)] = &[];
+}
+
+impl Piece for King {
+    const SLIDES: &'static [(isize, isize)] = &[];
+    const LEAPS: &'static [(isize, isize)] = ALL_DIRECTIONS;
+}
+
+impl Piece for Knight {
+    const SLIDES: &'static [(isize, isize)] = &[];
+    const LEAPS: &'static [(isize, isize)] = KNIGHT_LEAPS;
+}
+
+impl Piece for Queen {
+    const SLIDES: &'static [(isize, isize)] = ALL_DIRECTIONS;
+    const LEAPS: &'static [(isize, isize)] = &[];
+}
+
+impl Piece for Amazon {
+    const SLIDES: &'static [(isize, isize)] = ALL_DIRECTIONS;
+    const LEAPS: &'static [(isize, isize)] = KNIGHT_LEAPS;
+}
+
+/// The number of ways to place `pieces` pieces of kind `P` on a `width`×`height` board without
+/// any two attacking each other.
+///
+/// ```
+/// use nqueens::{Knight, Rook};
+///
+/// assert_eq!(nqueens::count_piece_placements::<Rook>(6, 6, 6), 720);
+/// // The knights all stand on squares of one colour.
+/// assert_eq!(nqueens::count_piece_placements::<Knight>(8, 8, 32), 2);
+/// ```
+pub fn count_piece_placements<P: Piece>(width: usize, height: usize, pieces: usize) -> usize {
+    with_row_set!(width * height, |S| PieceSearch::<S>::new::<P>(width, height).count(pieces))
+}
+
+/// Calls `f` with every way to place `pieces` pieces of kind `P` on a `width`×`height` board
+/// without any two attacking each other, as the squares of the pieces sorted row by row.
+///
+/// ```
+/// use nqueens::{King, Square};
+///
+/// let mut placements = vec![];
+/// nqueens::for_each_piece_placement::<King, _>(3, 2, 2, |squares| placements.push(squares.to_vec()));
+/// assert_eq!(placements, vec![
+///     vec![Square::new(0, 0), Square::new(2, 0)],
+///     vec![Square::new(0, 0), Square::new(2, 1)],
+///     vec![Square::new(2, 0), Square::new(0, 1)],
+///     vec![Square::new(0, 1), Square::new(2, 1)],
+/// ]);
+/// ```
+pub fn for_each_piece_placement<P: Piece, F>(width: usize, height: usize, pieces: usize, mut f: F)
+    where F: FnMut(&[Square])
+{
+    with_row_set!(width * height, |S| PieceSearch::<S>::new::<P>(width, height).for_each(pieces, &mut f))
+}
+
+/// A search through the squares of a board, numbered row by row from the top-left corner.
+struct PieceSearch<S> {
+    width: usize,
+    /// For each square, the later squares a piece on it attacks.
+    attacks: Vec<S>,
+}
+
+impl<S: RowSet> PieceSearch<S> {
+    fn new<P: Piece>(width: usize, height: usize) -> PieceSearch<S> {
+        let squares = width * height;
+        let square = |i: usize| Square::new(i % width, i / width);
+        let attacks = (0..squares)
+            .map(|i| {
+                let mut attacked = S::empty(squares);
+                for j in (i + 1..squares).filter(|&j| P::attacks(square(i), square(j))) {
+                    attacked.insert(j);
+                }
+                attacked
+            })
+            .collect();
+        PieceSearch { width, attacks }
+    }
+
+    fn square(&self, i: usize) -> Square {
+        Square::new(i % self.width, i / self.width)
+    }
+
+    /// The squares still free after putting the first piece on square `first`.
+    fn free_after(&self, first: usize) -> S {
+        let squares = self.attacks.len();
+        let mut free = S::empty(squares);
+        for i in first + 1..squares {
+            free.insert(i);
+        }
+        free.difference(&self.attacks[first])
+    }
+
+    /// Counts the placements, searching each square for the first piece in parallel.
+    fn count(&self, pieces: usize) -> usize {
+        if pieces == 0 {
+            return 1;
+        }
+        (0..self.attacks.len()).into_par_iter()
+            .map(|first| self.count_from(&self.free_after(first), pieces - 1))
+            .sum()
+    }
+
+    fn count_from(&self, free: &S, pieces_left: usize) -> usize {
+        if pieces_left == 0 {
+            return 1;
+        }
+        let mut count = 0;
+        let mut candidates = free.clone();
+        while candidates.len() >= pieces_left {
+            let square = candidates.pop_first().expect("candidates can't be empty");
+            count += self.count_from(&candidates.difference(&self.attacks[square]), pieces_left - 1);
+        }
+        count
+    }
+
+    fn for_each<F>(&self, pieces: usize, f: &mut F)
+        where F: FnMut(&[Square])
+    {
+        let mut free = S::empty(self.attacks.len());
+        for i in 0..self.attacks.len() {
+            free.insert(i);
+        }
+        self.for_each_from(&free, &mut vec![], pieces, f);
+    }
+
+    fn for_each_from<F>(&self, free: &S, squares: &mut Vec<Square>, pieces_left: usize, f: &mut F)
+        where F: FnMut(&[Square])
+    {
+        if pieces_left == 0 {
+            f(squares);
+            return;
+        }
+        let mut candidates = free.clone();
+        while candidates.len() >= pieces_left {
+            let square = candidates.pop_first().expect("candidates can't be empty");
+            squares.push(self.square(square));
+            self.for_each_from(&candidates.difference(&self.attacks[square]), squares, pieces_left - 1, f);
+            squares.pop();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search::count_placements;
+
+    /// The number of ways to place `n` pieces on an `n`×`n` board, from `n = 1`.
+    fn square_counts<P: Piece>(max_side_size: usize) -> Vec<usize> {
+        (1..=max_side_size).map(|n| count_piece_placements::<P>(n, n, n)).collect()
+    }
+
+    #[test]
+    fn test_piece_counts() {
+        assert_eq!(square_counts::<Rook>(8), [1, 2, 6, 24, 120, 720, 5040, 40320]);
+        assert_eq!(square_counts::<Bishop>(7), [1, 4, 26, 260, 3368, 53744, 1022320]);
+        assert_eq!(square_counts::<King>(7), [1, 0, 8, 79, 1974, 62266, 2484382]);
+        assert_eq!(square_counts::<Knight>(7), [1, 6, 36, 412, 9386, 257318, 8891854]);
+        assert_eq!(square_counts::<Queen>(8), [1, 0, 0, 2, 10, 4, 40, 92]);
+        // OEIS A051223: the first amazons fit on a 10×10 board.
+        assert_eq!(square_counts::<Amazon>(11), [1, 0, 0, 0, 0, 0, 0, 0, 0, 4, 44]);
+    }
+
+    #[test]
+    fn test_piece_placements() {
+        for (width, height) in [(0, 0), (1, 3), (4, 4), (5, 3), (2, 6)] {
+            for queens in 0..=width.min(height) + 1 {
+                assert_eq!(count_piece_placements::<Queen>(width, height, queens), count_placements(width, height, queens));
+            }
+        }
+
+        // The maximum numbers of kings and bishops on an 8×8 board, and the ways to place them.
+        assert_eq!(count_piece_placements::<King>(8, 8, 16), 281571);
+        assert_eq!(count_piece_placements::<King>(8, 8, 17), 0);
+        assert_eq!(count_piece_placements::<Bishop>(8, 8, 14), 256);
+        assert_eq!(count_piece_placements::<Bishop>(8, 8, 15), 0);
+        // Every rook placement on a board too big for the `u128` search.
+        assert_eq!(count_piece_placements::<Rook>(12, 12, 2), 144 * 121 / 2);
+
+        let mut placements = 0;
+        for_each_piece_placement::<Knight, _>(4, 3, 4, |squares| {
+            assert!(squares.windows(2).all(|w| (w[0].y, w[0].x) < (w[1].y, w[1].x)));
+            for (i, &a) in squares.iter().enumerate() {
+                assert!(squares[i + 1..].iter().all(|&b| !Knight::attacks(a, b) && !Knight::attacks(b, a)));
+            }
+            placements += 1;
+        });
+        assert_eq!(placements, count_piece_placements::<Knight>(4, 3, 4));
+    }
+
+    #[test]
+    fn test_attacks() {
+        let center = Square::new(3, 3);
+        assert!(Rook::attacks(center, Square::new(3, 0)));
+        assert!(!Rook::attacks(center, Square::new(4, 4)));
+        assert!(Bishop::attacks(center, Square::new(0, 6)));
+        assert!(!Bishop::attacks(center, Square::new(3, 5)));
+        assert!(King::attacks(center, Square::new(2, 4)));
+        assert!(!King::attacks(center, Square::new(1, 3)));
+        assert!(Knight::attacks(center, Square::new(5, 2)));
+        assert!(!Knight::attacks(center, Square::new(5, 5)));
+        assert!(Amazon::attacks(center, Square::new(1, 4)) && Amazon::attacks(center, Square::new(7, 7)));
+        assert!(!Amazon::attacks(center, Square::new(0, 5)));
+        assert!(!Queen::attacks(center, center));
+    }
+}
@@ -0,0 +1,499 @@
+use crate::board::{Board, Queen};
+use crate::bitboard::{with_row_set, BitSearch, BitSolutions, Constraints, RowSet, WideRowSet, PARALLEL_DEPTH};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use rayon::prelude::*;
+
+/// The search engine used to enumerate and count solutions.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Backend {
+    /// Builds each partial [`Board`] and re-checks it with [`Board::is_valid`] after every
+    /// insertion. Slow, but easy to check by hand.
+    Board,
+    /// Bitmask backtracking over `u64`s. Supports boards up to 64 wide.
+    Bits64,
+    /// Bitmask backtracking over `u128`s. Supports boards up to 128 wide.
+    Bits128,
+    /// Bitmask backtracking over heap-allocated masks. Supports boards of any size.
+    BitsWide,
+    /// The narrowest bitmask backend that fits the board.
+    #[default]
+    Auto,
+}
+
+impl Backend {
+    /// Resolves `Auto` to the concrete backend used for `side_size`.
+    pub fn for_side_size(self, side_size: usize) -> Backend {
+        match self {
+            Backend::Auto => Backend::for_bits(side_size),
+            backend => backend,
+        }
+    }
+
+    /// The narrowest bitmask backend whose row sets hold `bits` rows. Searches that keep sets of
+    /// squares rather than rows pass the number of squares.
+    pub(crate) fn for_bits(bits: usize) -> Backend {
+        match bits {
+            0..=64 => Backend::Bits64,
+            65..=128 => Backend::Bits128,
+            _ => Backend::BitsWide,
+        }
+    }
+}
+
+/// Which part of the search space the counting functions explore.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum SearchMode {
+    /// Visit every board.
+    Exhaustive,
+    /// Flipping a board top to bottom turns each solution into another one with the first
+    /// column's queen mirrored, so only search the top half of the first column and count each
+    /// solution twice. On odd boards the middle row mirrors onto itself and is counted once.
+    #[default]
+    Mirror,
+}
+
+impl SearchMode {
+    /// How many solutions each solution found with its first queen on `first_row` stands for.
+    pub(crate) fn weight(self, side_size: usize, first_row: usize) -> usize {
+        match self {
+            SearchMode::Exhaustive => 1,
+            SearchMode::Mirror if first_row < side_size / 2 => 2,
+            SearchMode::Mirror if first_row < side_size.div_ceil(2) => 1,
+            SearchMode::Mirror => 0,
+        }
+    }
+}
+
+/// Calls `report` with every complete board reachable from `base_board` by filling the columns
+/// from `col` onwards, along with its 1-based board number. Returns the number of boards found.
+///
+/// The columns are searched in parallel, so `report` is called from several threads at once and
+/// the board numbers reflect the order boards were found in rather than any board ordering.
+pub fn find_valid_boards<F>(base_board: &Board, col: usize, report: F) -> usize
+    where F: Fn(&Board, usize) + Sync
+{
+    let num_boards = AtomicUsize::new(0);
+    find_valid_boards_inner(base_board, col, &num_boards, &report);
+    num_boards.load(Ordering::SeqCst)
+}
+
+fn find_valid_boards_inner<F>(
+    base_board: &Board,
+    col: usize,
+    num_boards: &AtomicUsize,
+    report: &F,
+)
+    where F: Fn(&Board, usize) + Sync
+{
+    if base_board.is_complete() {
+        let board_num = 1 + num_boards.fetch_add(1, Ordering::SeqCst);
+        report(base_board, board_num);
+        return;
+    }
+
+    base_board.parallel_valid_direct_children_with_queen_in_col(col)
+        .for_each(|child_board| find_valid_boards_inner(&child_board, col + 1, num_boards, report));
+}
+
+/// Returns the first complete board reachable from `base_board` by filling the columns from
+/// `col` onwards, trying rows from top to bottom.
+pub fn find_first_valid_board(base_board: &Board, col: usize) -> Option<Board> {
+    if base_board.is_complete() {
+        return Some(base_board.clone());
+    }
+
+    base_board.valid_direct_children_with_queen_in_col(col)
+        .find_map(|child_board| find_first_valid_board(&child_board, col + 1))
+}
+
+/// Calls `f` with every solution of the `side_size`-queens problem, in no particular order.
+pub fn for_each_solution<F>(side_size: usize, f: F)
+    where F: Fn(&Board) + Sync
+{
+    for_each_solution_with(side_size, Backend::default(), f)
+}
+
+/// Like [`for_each_solution`], but searching with the given backend.
+///
+/// # Panics
+///
+/// Panics if `backend` is a fixed-width bitmask backend too narrow for `side_size`.
+pub fn for_each_solution_with<F>(side_size: usize, backend: Backend, f: F)
+    where F: Fn(&Board) + Sync
+{
+    match backend.for_side_size(side_size) {
+        Backend::Board => {
+            find_valid_boards(&Board::new(side_size), 0, |board, _| f(board));
+        }
+        Backend::Bits64 => BitSearch::<u64>::new(side_size).for_each_solution(&f),
+        Backend::Bits128 => BitSearch::<u128>::new(side_size).for_each_solution(&f),
+        Backend::BitsWide => BitSearch::<WideRowSet>::new(side_size).for_each_solution(&f),
+        Backend::Auto => unreachable!(),
+    }
+}
+
+/// The number of solutions of the `side_size`-queens problem.
+pub fn count_solutions(side_size: usize) -> usize {
+    count_solutions_with(side_size, Backend::default())
+}
+
+/// Like [`count_solutions`], but searching with the given backend.
+///
+/// # Panics
+///
+/// Panics if `backend` is a fixed-width bitmask backend too narrow for `side_size`.
+pub fn count_solutions_with(side_size: usize, backend: Backend) -> usize {
+    count_solutions_with_mode(side_size, backend, SearchMode::default())
+}
+
+/// Like [`count_solutions_with`], but only exploring the part of the search space `mode` asks
+/// for.
+///
+/// # Panics
+///
+/// Panics if `backend` is a fixed-width bitmask backend too narrow for `side_size`.
+pub fn count_solutions_with_mode(side_size: usize, backend: Backend, mode: SearchMode) -> usize {
+    match backend.for_side_size(side_size) {
+        Backend::Board if side_size == 0 => 1,
+        Backend::Board => {
+            (0..side_size)
+                .map(|row| (row, mode.weight(side_size, row)))
+                .filter(|&(_, weight)| weight > 0)
+                .map(|(row, weight)| {
+                    let board = Board::new(side_size).try_insert_queen(Queen::new(0, row)).unwrap();
+                    weight * find_valid_boards(&board, 1, |_, _| ())
+                })
+                .sum()
+        }
+        Backend::Bits64 => BitSearch::<u64>::new(side_size).count_solutions(mode),
+        Backend::Bits128 => BitSearch::<u128>::new(side_size).count_solutions(mode),
+        Backend::BitsWide => BitSearch::<WideRowSet>::new(side_size).count_solutions(mode),
+        Backend::Auto => unreachable!(),
+    }
+}
+
+/// The first solution of the `side_size`-queens problem in row order, or `None` if there isn't
+/// one.
+pub fn first_solution(side_size: usize) -> Option<Board> {
+    first_solution_with(side_size, Backend::default())
+}
+
+/// Like [`first_solution`], but searching with the given backend.
+///
+/// # Panics
+///
+/// Panics if `backend` is a fixed-width bitmask backend too narrow for `side_size`.
+pub fn first_solution_with(side_size: usize, backend: Backend) -> Option<Board> {
+    match backend.for_side_size(side_size) {
+        Backend::Board => find_first_valid_board(&Board::new(side_size), 0),
+        Backend::Bits64 => BitSearch::<u64>::new(side_size).first_solution(),
+        Backend::Bits128 => BitSearch::<u128>::new(side_size).first_solution(),
+        Backend::BitsWide => BitSearch::<WideRowSet>::new(side_size).first_solution(),
+        Backend::Auto => unreachable!(),
+    }
+}
+
+/// A lazy iterator over the solutions of the n-queens problem or one of its variants. Created by
+/// [`Board::solutions`], [`Board::placements`] and [`Board::completions`].
+#[derive(Debug, Clone)]
+pub struct Solutions {
+    inner: SolutionsInner,
+}
+
+#[derive(Debug, Clone)]
+enum SolutionsInner {
+    Bits64(BitSolutions<u64>),
+    Bits128(BitSolutions<u128>),
+    BitsWide(BitSolutions<WideRowSet>),
+}
+
+impl Solutions {
+    /// The solutions whose first queens are on `prefix`, which must be a valid placement.
+    pub(crate) fn from_prefix(side_size: usize, prefix: Vec<usize>) -> Solutions {
+        let shape = Shape::square(side_size);
+        Solutions::constrained(shape, &Constraints::default(), prefix.into_iter().map(Some).collect())
+    }
+
+    /// The placements of `shape` meeting `constraints` that start with the queens on `prefix`,
+    /// one row or `None` per column. The prefix must be a valid placement meeting them too.
+    pub(crate) fn constrained(shape: Shape, constraints: &Constraints, prefix: Vec<Option<usize>>) -> Solutions {
+        let inner = match Backend::for_bits(shape.bits()) {
+            Backend::Bits64 => SolutionsInner::Bits64(shape.search(constraints).solutions(prefix)),
+            Backend::Bits128 => SolutionsInner::Bits128(shape.search(constraints).solutions(prefix)),
+            _ => SolutionsInner::BitsWide(shape.search(constraints).solutions(prefix)),
+        };
+        Solutions { inner }
+    }
+}
+
+impl Iterator for Solutions {
+    type Item = Board;
+
+    fn next(&mut self) -> Option<Board> {
+        match &mut self.inner {
+            SolutionsInner::Bits64(solutions) => solutions.next(),
+            SolutionsInner::Bits128(solutions) => solutions.next(),
+            SolutionsInner::BitsWide(solutions) => solutions.next(),
+        }
+    }
+}
+
+impl Board {
+    /// Lazily yields every solution of the `side_size`-queens problem exactly once, ordered by
+    /// the rows of their queens from the leftmost column rightwards.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let first = Board::solutions(8).next().unwrap();
+    /// assert!(first.is_complete() && first.is_valid());
+    /// assert_eq!(Board::solutions(8).count(), 92);
+    /// ```
+    pub fn solutions(side_size: usize) -> Solutions {
+        Solutions::from_prefix(side_size, vec![])
+    }
+
+    /// Parallel version of [`solutions`](Board::solutions). Yields every solution exactly once,
+    /// in no particular order.
+    pub fn par_solutions(side_size: usize) -> impl ParallelIterator<Item=Board> {
+        par_constrained_solutions(Shape::square(side_size), Constraints::default())
+    }
+
+    /// Lazily yields every way to place `queens` queens on a `width`×`height` board without any
+    /// two attacking each other. Boards at least as tall as they're wide come in row order.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// assert_eq!(Board::placements(4, 3, 3).count(), 4);
+    /// assert_eq!(Board::placements(8, 8, 8).count(), 92);
+    /// assert!(Board::placements(6, 6, 2).all(|b| b.is_valid() && b.queens().len() == 2));
+    /// ```
+    pub fn placements(width: usize, height: usize, queens: usize) -> Solutions {
+        Solutions::constrained(Shape { width, height, queens }, &Constraints::default(), vec![])
+    }
+
+    /// Parallel version of [`placements`](Board::placements), yielding the placements in no
+    /// particular order.
+    pub fn par_placements(width: usize, height: usize, queens: usize) -> impl ParallelIterator<Item=Board> {
+        par_constrained_solutions(Shape { width, height, queens }, Constraints::default())
+    }
+}
+
+/// The number of ways to place `queens` queens on a `width`×`height` board without any two
+/// attacking each other.
+///
+/// ```
+/// // Two rows fit at most two queens, which need at least one column between them.
+/// assert_eq!(nqueens::count_placements(4, 2, 2), 6);
+/// assert_eq!(nqueens::count_placements(4, 2, 3), 0);
+/// ```
+pub fn count_placements(width: usize, height: usize, queens: usize) -> usize {
+    count_constrained_solutions(Shape { width, height, queens }, &Constraints::default())
+}
+
+/// The number of ways to place any number of queens on a `width`×`height` board without any two
+/// attacking each other, in a single search. Entry `k` is
+/// [`count_placements(width, height, k)`](count_placements), for every `k` up to the smaller side
+/// of the board, beyond which no placements exist. The entries are the coefficients of the
+/// independence polynomial of the queens graph.
+///
+/// ```
+/// assert_eq!(nqueens::count_placements_table(4, 4), vec![1, 16, 44, 24, 2]);
+/// ```
+pub fn count_placements_table(width: usize, height: usize) -> Vec<usize> {
+    let shape = Shape { width, height, queens: width.min(height) };
+    let constraints = Constraints::default();
+    with_row_set!(shape.bits(), |S| shape.search::<S>(&constraints).count_by_queens())
+}
+
+/// The size of a board and the number of queens to put on it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) struct Shape {
+    pub width: usize,
+    pub height: usize,
+    pub queens: usize,
+}
+
+impl Shape {
+    /// A square board with one queen per column.
+    pub fn square(side_size: usize) -> Shape {
+        Shape { width: side_size, height: side_size, queens: side_size }
+    }
+
+    /// The number of rows the row sets of a search of the board have to hold.
+    fn bits(self) -> usize {
+        self.width.max(self.height)
+    }
+
+    fn search<S: RowSet>(self, constraints: &Constraints) -> BitSearch<S> {
+        BitSearch::with_shape(self.width, self.height, self.queens, constraints)
+    }
+}
+
+/// Every placement of `shape` meeting `constraints`, searched in parallel.
+pub(crate) fn par_constrained_solutions(shape: Shape, constraints: Constraints) -> impl ParallelIterator<Item=Board> {
+    let prefixes = with_row_set!(shape.bits(), |S| shape.search::<S>(&constraints).prefixes(PARALLEL_DEPTH));
+    prefixes.into_par_iter()
+        .flat_map_iter(move |prefix| Solutions::constrained(shape, &constraints, prefix))
+}
+
+/// The number of placements of `shape` meeting `constraints`, searched in parallel.
+pub(crate) fn count_constrained_solutions(shape: Shape, constraints: &Constraints) -> usize {
+    let mode = SearchMode::default();
+    with_row_set!(shape.bits(), |S| shape.search::<S>(constraints).count_solutions(mode))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_first_valid_board() {
+        assert!(first_solution(3).is_none());
+        let board = first_solution(8).unwrap();
+        assert!(board.is_complete());
+        assert!(board.is_valid());
+    }
+
+    /// OEIS A000170: the number of solutions of the n-queens problem, from n = 1.
+    const A000170: [usize; 16] = [
+        1, 0, 0, 2, 10, 4, 40, 92, 352, 724, 2680, 14200, 73712, 365596, 2279184, 14772512,
+    ];
+
+    #[test]
+    fn test_count_solutions() {
+        for (side_size, &expected) in (1..).zip(&A000170) {
+            assert_eq!(count_solutions(side_size), expected, "side size {}", side_size);
+        }
+        assert_eq!(count_solutions(0), 1);
+    }
+
+    const BACKENDS: [Backend; 5] = [
+        Backend::Board,
+        Backend::Bits64,
+        Backend::Bits128,
+        Backend::BitsWide,
+        Backend::Auto,
+    ];
+
+    #[test]
+    fn test_for_each_solution() {
+        use std::sync::Mutex;
+        let mut expected = None;
+        for &backend in &BACKENDS {
+            let boards = Mutex::new(vec![]);
+            for_each_solution_with(6, backend, |board| boards.lock().unwrap().push(board.clone()));
+            let mut boards = boards.into_inner().unwrap();
+            boards.sort_by(|a, b| a.queens().cmp(b.queens()));
+            boards.dedup();
+            assert_eq!(boards.len(), 4);
+            assert!(boards.iter().all(|b| b.is_complete() && b.is_valid()));
+            assert_eq!(*expected.get_or_insert_with(|| boards.clone()), boards);
+        }
+    }
+
+    #[test]
+    fn test_backends_agree() {
+        for &backend in &BACKENDS {
+            for &mode in &[SearchMode::Exhaustive, SearchMode::Mirror] {
+                let counts: Vec<_> = (0..=9).map(|n| count_solutions_with_mode(n, backend, mode)).collect();
+                assert_eq!(counts, vec![1, 1, 0, 0, 2, 10, 4, 40, 92, 352], "{:?} {:?}", backend, mode);
+            }
+            assert_eq!(first_solution_with(8, backend), first_solution_with(8, Backend::Board));
+            assert_eq!(first_solution_with(3, backend), None);
+        }
+    }
+
+    #[test]
+    fn test_solutions() {
+        let mut solutions: Vec<_> = Board::solutions(9).collect();
+        assert_eq!(solutions.len(), 352);
+        assert!(solutions.windows(2).all(|w| w[0].queens() < w[1].queens()));
+        assert_eq!(solutions.first(), first_solution(9).as_ref());
+
+        let mut par_solutions: Vec<_> = Board::par_solutions(9).collect();
+        par_solutions.sort_by(|a, b| a.queens().cmp(b.queens()));
+        assert_eq!(par_solutions, solutions);
+
+        solutions.retain(|b| b.queens()[0].y == 0);
+        let par_filtered: Vec<_> = Board::par_solutions(9)
+            .filter(|b| b.queens()[0].y == 0)
+            .collect();
+        assert_eq!(par_filtered.len(), solutions.len());
+
+        assert_eq!(Board::solutions(2).next(), None);
+        assert_eq!(Board::par_solutions(1).count(), 1);
+    }
+
+    #[test]
+    fn test_wide_boards() {
+        assert_eq!(Backend::Auto.for_side_size(64), Backend::Bits64);
+        assert_eq!(Backend::Auto.for_side_size(70), Backend::Bits128);
+        assert_eq!(Backend::Auto.for_side_size(129), Backend::BitsWide);
+        assert_eq!(Backend::Board.for_side_size(129), Backend::Board);
+        assert_eq!((Backend::for_bits(0), Backend::for_bits(128)), (Backend::Bits64, Backend::Bits128));
+        assert_eq!(with_row_set!(65, |S| S::MAX_SIDE_SIZE), Some(128));
+        assert_eq!(with_row_set!(129, |S| S::MAX_SIDE_SIZE), None);
+        assert_eq!(first_solution_with(20, Backend::BitsWide), first_solution_with(20, Backend::Bits64));
+    }
+
+    /// Counts the placements of `queens` queens on the squares from `cell` onwards by trying
+    /// every square, for checking the bitmask search against.
+    fn count_placements_by_brute_force(board: &Board, cell: usize, queens: usize) -> usize {
+        if queens == 0 {
+            return 1;
+        }
+        (cell..board.width() * board.height())
+            .filter_map(|cell| {
+                let queen = Queen::new(cell % board.width(), cell / board.width());
+                board.try_insert_queen(queen).map(|board| (cell, board))
+            })
+            .map(|(cell, board)| count_placements_by_brute_force(&board, cell + 1, queens - 1))
+            .sum()
+    }
+
+    #[test]
+    fn test_placements() {
+        for width in 0..=6 {
+            for height in 0..=6 {
+                for queens in 0..=width.min(height) + 1 {
+                    let expected = count_placements_by_brute_force(&Board::rect(width, height), 0, queens);
+                    let shape = (width, height, queens);
+                    assert_eq!(count_placements(width, height, queens), expected, "{:?}", shape);
+
+                    let placements: Vec<_> = Board::placements(width, height, queens).collect();
+                    assert_eq!(placements.len(), expected, "{:?}", shape);
+                    assert!(placements.iter().all(|b| b.is_valid() && b.queens().len() == queens), "{:?}", shape);
+                    assert!(placements.iter().all(|b| (b.width(), b.height()) == (width, height)), "{:?}", shape);
+                    if width <= height {
+                        assert!(placements.windows(2).all(|w| w[0].queens() < w[1].queens()), "{:?}", shape);
+                    }
+                    assert_eq!(Board::par_placements(width, height, queens).count(), expected, "{:?}", shape);
+                }
+            }
+        }
+
+        // The number of ways to place k queens on the standard board, from k = 0.
+        let counts: Vec<_> = (0..=9).map(|queens| count_placements(8, 8, queens)).collect();
+        assert_eq!(counts, vec![1, 64, 1288, 10320, 34568, 46736, 22708, 3192, 92, 0]);
+        assert_eq!(count_placements(12, 7, 7), count_placements(7, 12, 7));
+    }
+
+    #[test]
+    fn test_count_placements_table() {
+        assert_eq!(count_placements_table(8, 8), vec![1, 64, 1288, 10320, 34568, 46736, 22708, 3192, 92]);
+        assert_eq!(count_placements_table(0, 0), vec![1]);
+        assert_eq!(count_placements_table(3, 0), vec![1]);
+        for (width, height) in [(1, 1), (2, 5), (6, 3), (7, 7), (9, 10)] {
+            let expected: Vec<_> = (0..=width.min(height)).map(|k| count_placements(width, height, k)).collect();
+            assert_eq!(count_placements_table(width, height), expected, "{}x{}", width, height);
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn test_backend_too_narrow() {
+        count_solutions_with(65, Backend::Bits64);
+    }
+}
@@ -0,0 +1,256 @@
+//! The symmetries of a square board: the dihedral group D4 of four rotations and four
+//! reflections. Every solution of the n-queens problem is carried onto another solution by each
+//! of them, so the solutions fall into orbits of 1, 2, 4 or 8 boards. Picking one board out of
+//! each orbit gives the fundamental solutions.
+
+use crate::board::{Board, Queen};
+use crate::search::Solutions;
+use rayon::prelude::*;
+
+/// One of the eight symmetries of a square board. Rotations are clockwise as the board is drawn
+/// by [`Board::get_board_string`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Symmetry {
+    Identity,
+    Rotate90,
+    Rotate180,
+    Rotate270,
+    /// Mirrors the board left to right.
+    FlipHorizontal,
+    /// Mirrors the board top to bottom.
+    FlipVertical,
+    /// Mirrors the board across the diagonal from the top-left to the bottom-right corner.
+    FlipDiagonal,
+    /// Mirrors the board across the diagonal from the bottom-left to the top-right corner.
+    FlipAntiDiagonal,
+}
+
+impl Symmetry {
+    pub const ALL: [Symmetry; 8] = [
+        Symmetry::Identity,
+        Symmetry::Rotate90,
+        Symmetry::Rotate180,
+        Symmetry::Rotate270,
+        Symmetry::FlipHorizontal,
+        Symmetry::FlipVertical,
+        Symmetry::FlipDiagonal,
+        Symmetry::FlipAntiDiagonal,
+    ];
+
+    /// Where `queen` ends up on a `side_size`-wide board after applying the symmetry.
+    pub fn apply(self, queen: Queen, side_size: usize) -> Queen {
+        let Queen { x, y } = queen;
+        let last = side_size - 1;
+        match self {
+            Symmetry::Identity => Queen::new(x, y),
+            Symmetry::Rotate90 => Queen::new(last - y, x),
+            Symmetry::Rotate180 => Queen::new(last - x, last - y),
+            Symmetry::Rotate270 => Queen::new(y, last - x),
+            Symmetry::FlipHorizontal => Queen::new(last - x, y),
+            Symmetry::FlipVertical => Queen::new(x, last - y),
+            Symmetry::FlipDiagonal => Queen::new(y, x),
+            Symmetry::FlipAntiDiagonal => Queen::new(last - y, last - x),
+        }
+    }
+}
+
+/// A fundamental solution along with the number of distinct solutions its symmetries produce.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct FundamentalSolution {
+    /// The orbit's canonical board; see [`Board::canonical`].
+    pub board: Board,
+    pub orbit_size: usize,
+}
+
+impl Board {
+    /// The board with every queen, pawn and blocked square moved by `symmetry`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the board isn't square, since most symmetries would move squares off it.
+    pub fn transform(&self, symmetry: Symmetry) -> Board {
+        let side_size = self.side_size();
+        let apply = |squares: &[Queen]| squares.iter().map(|&s| symmetry.apply(s, side_size)).collect::<Vec<_>>();
+        Board::with_queens(side_size, apply(self.queens()))
+            .and_then(|board| board.with_geometry(self.geometry()))
+            .and_then(|board| board.with_pawns(apply(self.pawns())))
+            .and_then(|board| board.with_blocked_squares(apply(self.blocked_squares())))
+            .expect("symmetry moved a queen off the board")
+    }
+
+    /// The distinct boards this board can be turned into by rotating and reflecting it,
+    /// including itself.
+    pub fn orbit(&self) -> Vec<Board> {
+        let mut orbit: Vec<Board> = Symmetry::ALL.iter().map(|&s| self.transform(s)).collect();
+        orbit.sort_by(|a, b| a.symmetry_key().cmp(&b.symmetry_key()));
+        orbit.dedup();
+        orbit
+    }
+
+    /// The board in this board's orbit whose queens sort first, then its pawns and then its
+    /// blocked squares. Two boards are rotations or reflections of each other exactly when they
+    /// have the same canonical form.
+    pub fn canonical(&self) -> Board {
+        Symmetry::ALL.iter()
+            .map(|&s| self.transform(s))
+            .min_by(|a, b| a.symmetry_key().cmp(&b.symmetry_key()))
+            .unwrap()
+    }
+
+    /// Whether the board is its own canonical form.
+    pub fn is_canonical(&self) -> bool {
+        Symmetry::ALL.iter().all(|&s| self.symmetry_key() <= self.transform(s).symmetry_key())
+    }
+
+    /// What the boards of an orbit are ordered by. The symmetries keep the geometry, so it's
+    /// left out.
+    fn symmetry_key(&self) -> (&[Queen], &[Queen], &[Queen]) {
+        (self.queens(), self.pawns(), self.blocked_squares())
+    }
+
+    /// Lazily yields one solution of the `side_size`-queens problem from each orbit, in row
+    /// order.
+    ///
+    /// Flipping a board top to bottom moves the first column's queen from row `r` to row
+    /// `side_size - 1 - r`, so every canonical board has its first queen in the top half of the
+    /// column and the search never visits the bottom half.
+    ///
+    /// ```
+    /// use nqueens::Board;
+    ///
+    /// let fundamental: Vec<_> = Board::fundamental_solutions(8).collect();
+    /// assert_eq!(fundamental.len(), 12);
+    /// assert_eq!(fundamental.iter().map(|s| s.orbit_size).sum::<usize>(), 92);
+    /// ```
+    pub fn fundamental_solutions(side_size: usize) -> FundamentalSolutions {
+        // The empty board has no first column to restrict.
+        let solutions = if side_size == 0 { Some(Board::solutions(0)) } else { None };
+        FundamentalSolutions {
+            side_size,
+            next_first_row: 0,
+            solutions,
+        }
+    }
+
+    /// The number of fundamental solutions of the `side_size`-queens problem, searching the top
+    /// half of the first column in parallel.
+    pub fn count_fundamental_solutions(side_size: usize) -> usize {
+        if side_size == 0 {
+            return 1;
+        }
+        (0..first_row_limit(side_size)).into_par_iter()
+            .map(|row| {
+                Solutions::from_prefix(side_size, vec![row])
+                    .filter(Board::is_canonical)
+                    .count()
+            })
+            .sum()
+    }
+}
+
+/// One past the last first-column row a canonical board can use.
+fn first_row_limit(side_size: usize) -> usize {
+    side_size.div_ceil(2)
+}
+
+/// An iterator over the fundamental solutions of the n-queens problem. Created by
+/// [`Board::fundamental_solutions`].
+#[derive(Debug, Clone)]
+pub struct FundamentalSolutions {
+    side_size: usize,
+    next_first_row: usize,
+    solutions: Option<Solutions>,
+}
+
+impl Iterator for FundamentalSolutions {
+    type Item = FundamentalSolution;
+
+    fn next(&mut self) -> Option<FundamentalSolution> {
+        loop {
+            if let Some(solutions) = &mut self.solutions {
+                if let Some(board) = solutions.find(Board::is_canonical) {
+                    let orbit_size = board.orbit().len();
+                    return Some(FundamentalSolution { board, orbit_size });
+                }
+            }
+
+            if self.next_first_row >= first_row_limit(self.side_size) {
+                return None;
+            }
+            self.solutions = Some(Solutions::from_prefix(self.side_size, vec![self.next_first_row]));
+            self.next_first_row += 1;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::Square;
+
+    /// OEIS A002562: the number of fundamental solutions of the n-queens problem, from n = 1.
+    const A002562: [usize; 14] = [1, 0, 0, 1, 2, 1, 6, 12, 46, 92, 341, 1787, 9233, 45752];
+
+    #[test]
+    fn test_transforms() {
+        let board = Board::from_rows(&[1, 3, 0, 2]).unwrap();
+        assert_eq!(board.transform(Symmetry::Identity), board);
+        assert_eq!(board.transform(Symmetry::FlipVertical), Board::from_rows(&[2, 0, 3, 1]).unwrap());
+        assert_eq!(board.transform(Symmetry::FlipHorizontal), Board::from_rows(&[2, 0, 3, 1]).unwrap());
+        assert_eq!(board.transform(Symmetry::Rotate90), board);
+
+        let corner = Board::with_queens(3, vec![Queen::new(0, 0)]).unwrap();
+        assert_eq!(corner.transform(Symmetry::Rotate90).queens(), &[Queen::new(2, 0)]);
+        assert_eq!(corner.transform(Symmetry::Rotate180).queens(), &[Queen::new(2, 2)]);
+        assert_eq!(corner.transform(Symmetry::Rotate270).queens(), &[Queen::new(0, 2)]);
+        assert_eq!(corner.transform(Symmetry::FlipDiagonal).queens(), &[Queen::new(0, 0)]);
+        assert_eq!(corner.transform(Symmetry::FlipAntiDiagonal).queens(), &[Queen::new(2, 2)]);
+        assert_eq!(corner.orbit().len(), 4);
+
+        let holed = corner.with_blocked_squares(vec![Square::new(1, 0)]).unwrap();
+        assert_eq!(holed.transform(Symmetry::Rotate90).blocked_squares(), &[Square::new(2, 1)]);
+        assert_eq!(holed.orbit().len(), 8);
+
+        // Turning this solution a quarter turn gives the same queens, but moves the blocked
+        // square to another corner.
+        let cornered = board.with_blocked_squares(vec![Square::new(0, 0)]).unwrap();
+        let orbit = cornered.orbit();
+        assert_eq!(orbit.len(), 8);
+        assert!(orbit.iter().all(|b| b.canonical() == orbit[0]));
+        assert_eq!(orbit.iter().filter(|b| b.is_canonical()).count(), 1);
+
+        for &s in &Symmetry::ALL {
+            let image = Board::from_rows(&[0, 4, 7, 5, 2, 6, 1, 3]).unwrap().transform(s);
+            assert!(image.is_complete() && image.is_valid());
+        }
+    }
+
+    #[test]
+    fn test_canonical() {
+        for board in Board::solutions(8) {
+            let canonical = board.canonical();
+            assert!(canonical.is_canonical());
+            assert!(board.orbit().contains(&canonical));
+            assert!(board.orbit().iter().all(|b| b.canonical() == canonical));
+        }
+    }
+
+    #[test]
+    fn test_fundamental_solutions() {
+        for (side_size, &expected) in (1..).zip(&A002562) {
+            assert_eq!(Board::count_fundamental_solutions(side_size), expected, "side size {}", side_size);
+        }
+
+        for side_size in 0..=9 {
+            let fundamental: Vec<_> = Board::fundamental_solutions(side_size).collect();
+            let orbit_total: usize = fundamental.iter().map(|s| s.orbit_size).sum();
+            assert_eq!(orbit_total, Board::solutions(side_size).count());
+
+            let mut canonical: Vec<_> = Board::solutions(side_size).map(|b| b.canonical()).collect();
+            canonical.sort_by(|a, b| a.queens().cmp(b.queens()));
+            canonical.dedup();
+            let boards: Vec<_> = fundamental.into_iter().map(|s| s.board).collect();
+            assert_eq!(boards, canonical);
+        }
+    }
+}
@@ -0,0 +1,109 @@
+//! The toroidal n-queens problem, where the board wraps around at its edges and the diagonals are
+//! taken mod `n`. Every solution of it also solves the ordinary problem, but far fewer boards
+//! have any: Pólya (1918) showed that `n` queens fit on an `n`×`n` torus exactly when `n` is
+//! coprime to 6. The counts are OEIS A007705.
+//!
+//! The searches are the ordinary bitmask ones, with the diagonal sets rotated rather than shifted
+//! from one column to the next. To solve a toroidal board with queens or blocked squares already
+//! on it, give it [`Geometry::Toroidal`] and use [`Board::completions`].
+
+use crate::bitboard::Constraints;
+use crate::board::{Board, Geometry};
+use crate::search::{count_constrained_solutions, par_constrained_solutions, Shape, Solutions};
+use rayon::prelude::*;
+
+impl Board {
+    /// Lazily yields every solution of the toroidal `side_size`-queens problem, in row order.
+    ///
+    /// ```
+    /// use nqueens::{Board, Geometry};
+    ///
+    /// let solutions: Vec<_> = Board::toroidal_solutions(5).collect();
+    /// assert_eq!(solutions.len(), 10);
+    /// assert!(solutions.iter().all(|b| b.geometry() == Geometry::Toroidal && b.is_valid()));
+    /// ```
+    pub fn toroidal_solutions(side_size: usize) -> Solutions {
+        Solutions::constrained(Shape::square(side_size), &toroidal(), vec![])
+    }
+
+    /// Parallel version of [`toroidal_solutions`](Board::toroidal_solutions), yielding the
+    /// solutions in no particular order.
+    pub fn par_toroidal_solutions(side_size: usize) -> impl ParallelIterator<Item=Board> {
+        par_constrained_solutions(Shape::square(side_size), toroidal())
+    }
+}
+
+/// The number of solutions of the toroidal `side_size`-queens problem.
+///
+/// ```
+/// assert_eq!(nqueens::count_toroidal_solutions(13), 4524);
+/// assert_eq!(nqueens::count_toroidal_solutions(12), 0);
+/// ```
+pub fn count_toroidal_solutions(side_size: usize) -> usize {
+    count_constrained_solutions(Shape::square(side_size), &toroidal())
+}
+
+fn toroidal() -> Constraints {
+    Constraints { geometry: Geometry::Toroidal, ..Constraints::default() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Queen, Square};
+    use crate::completion::CompletionError;
+
+    // OEIS A007705, starting from n = 1.
+    const TOROIDAL_SOLUTION_COUNTS: [usize; 17] = [
+        1, 0, 0, 0, 10, 0, 28, 0, 0, 0, 88, 0, 4524, 0, 0, 0, 140692,
+    ];
+
+    #[test]
+    fn test_count_toroidal_solutions() {
+        for (side_size, &expected) in (1..).zip(&TOROIDAL_SOLUTION_COUNTS) {
+            assert_eq!(count_toroidal_solutions(side_size), expected, "side size {}", side_size);
+            let coprime = side_size % 2 != 0 && side_size % 3 != 0;
+            assert_eq!(expected > 0, coprime, "side size {}", side_size);
+        }
+    }
+
+    #[test]
+    fn test_toroidal_solutions() {
+        for side_size in 0..=11 {
+            let expected: Vec<_> = Board::solutions(side_size)
+                .filter_map(|board| board.with_geometry(Geometry::Toroidal))
+                .filter(|board| board.is_valid())
+                .collect();
+            assert_eq!(Board::toroidal_solutions(side_size).collect::<Vec<_>>(), expected);
+
+            let mut par_solutions: Vec<_> = Board::par_toroidal_solutions(side_size).collect();
+            par_solutions.sort_by(|a, b| a.queens().cmp(b.queens()));
+            assert_eq!(par_solutions, expected);
+
+            for board in &expected {
+                assert!(board.orbit().iter().all(|b| b.geometry() == Geometry::Toroidal && b.is_valid()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_toroidal_completions() {
+        let board = Board::with_queens(11, vec![Queen::new(3, 5)]).unwrap()
+            .with_blocked_squares(vec![Square::new(0, 0), Square::new(7, 2)]).unwrap()
+            .with_geometry(Geometry::Toroidal).unwrap();
+        let expected: Vec<_> = Board::toroidal_solutions(11)
+            .filter(|solution| solution.queens().contains(&Queen::new(3, 5)))
+            .filter(|solution| solution.queens().iter().all(|&q| !board.is_blocked(q)))
+            .map(|solution| solution.with_blocked_squares(board.blocked_squares().iter().copied()).unwrap())
+            .collect();
+        assert!(!expected.is_empty());
+        assert_eq!(board.completions().unwrap().collect::<Vec<_>>(), expected);
+        assert_eq!(board.count_completions(), Ok(expected.len()));
+
+        // The two queens only attack each other across the edge of the board.
+        let board = Board::with_queens(7, vec![Queen::new(0, 1), Queen::new(6, 2)]).unwrap();
+        assert!(board.is_valid());
+        let board = board.with_geometry(Geometry::Toroidal).unwrap();
+        assert_eq!(board.count_completions(), Err(CompletionError::Attacking(Queen::new(0, 1), Queen::new(6, 2))));
+    }
+}
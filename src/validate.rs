@@ -0,0 +1,176 @@
+//! Explaining why a board isn't valid: rather than the yes or no of [`Board::is_valid`],
+//! [`Board::conflicts`] lists every pair of queens that attack each other along with the line
+//! they share, and every queen standing where no queen may.
+
+use crate::board::{Board, Queen};
+use std::fmt;
+
+/// One of the lines through a square that a queen attacks along.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Line {
+    Row,
+    Column,
+    /// The diagonal running from the top-left to the bottom-right, numbered by
+    /// [`Queen::sw_diagonal`].
+    SwDiagonal,
+    /// The diagonal running from the bottom-left to the top-right, numbered by
+    /// [`Queen::se_diagonal`].
+    SeDiagonal,
+}
+
+/// A reason a board isn't valid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Conflict {
+    /// Two queens attack each other along a line they share. The first queen comes first in the
+    /// board's order.
+    Attacking(Queen, Queen, Line),
+    /// A queen stands on a blocked square.
+    Blocked(Queen),
+    /// A queen stands on a pawn.
+    OnPawn(Queen),
+}
+
+impl fmt::Display for Line {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Line::Row => write!(f, "row"),
+            Line::Column => write!(f, "column"),
+            Line::SwDiagonal => write!(f, "top-left to bottom-right diagonal"),
+            Line::SeDiagonal => write!(f, "bottom-left to top-right diagonal"),
+        }
+    }
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Conflict::Attacking(a, b, line) => write!(
+                f,
+                "the queens at column {}, row {} and column {}, row {} share a {}",
+                a.x, a.y, b.x, b.y, line,
+            ),
+            Conflict::Blocked(queen) => write!(
+                f,
+                "the queen at column {}, row {} stands on a blocked square",
+                queen.x, queen.y,
+            ),
+            Conflict::OnPawn(queen) => write!(
+                f,
+                "the queen at column {}, row {} stands on a pawn",
+                queen.x, queen.y,
+            ),
+        }
+    }
+}
+
+impl Conflict {
+    /// The queens involved.
+    pub fn queens(&self) -> Vec<Queen> {
+        match *self {
+            Conflict::Attacking(a, b, _) => vec![a, b],
+            Conflict::Blocked(queen) | Conflict::OnPawn(queen) => vec![queen],
+        }
+    }
+}
+
+impl Board {
+    /// Everything that keeps the board from being valid, sorted. Empty exactly when
+    /// [`is_valid`](Board::is_valid) holds.
+    ///
+    /// On toroidal boards two queens can share both diagonals, which gives a conflict for each.
+    /// Pawns between two queens keep them from attacking each other, as usual.
+    ///
+    /// ```
+    /// use nqueens::{Board, Conflict, Line, Queen};
+    ///
+    /// let board = Board::from_rows(&[1, 3, 0, 1]).unwrap();
+    /// assert_eq!(board.conflicts(), vec![
+    ///     Conflict::Attacking(Queen::new(0, 1), Queen::new(3, 1), Line::Row),
+    ///     Conflict::Attacking(Queen::new(1, 3), Queen::new(3, 1), Line::SeDiagonal),
+    ///     Conflict::Attacking(Queen::new(2, 0), Queen::new(3, 1), Line::SwDiagonal),
+    /// ]);
+    /// ```
+    pub fn conflicts(&self) -> Vec<Conflict> {
+        let mut conflicts = vec![];
+        let queens = self.queens();
+        for (i, &a) in queens.iter().enumerate() {
+            if self.is_blocked(a) {
+                conflicts.push(Conflict::Blocked(a));
+            }
+            if self.has_pawn(a) {
+                conflicts.push(Conflict::OnPawn(a));
+            }
+            for &b in &queens[i + 1..] {
+                if !self.is_attacking(a, b) {
+                    continue;
+                }
+                let (a_diagonals, b_diagonals) = (self.diagonals(a), self.diagonals(b));
+                let shared = [
+                    (a.y == b.y, Line::Row),
+                    (a.x == b.x, Line::Column),
+                    (a_diagonals.0 == b_diagonals.0, Line::SwDiagonal),
+                    (a_diagonals.1 == b_diagonals.1, Line::SeDiagonal),
+                ];
+                for (_, line) in shared.iter().filter(|(shares, _)| *shares) {
+                    conflicts.push(Conflict::Attacking(a, b, *line));
+                }
+            }
+        }
+        conflicts.sort();
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::board::{Geometry, Square};
+
+    #[test]
+    fn test_conflicts() {
+        let board = Board::with_queens(5, vec![Queen::new(0, 0), Queen::new(2, 2), Queen::new(4, 0), Queen::new(2, 4)])
+            .unwrap()
+            .with_blocked_squares(vec![Square::new(2, 4)])
+            .unwrap();
+        assert_eq!(board.conflicts(), vec![
+            Conflict::Attacking(Queen::new(0, 0), Queen::new(2, 2), Line::SwDiagonal),
+            Conflict::Attacking(Queen::new(0, 0), Queen::new(4, 0), Line::Row),
+            Conflict::Attacking(Queen::new(2, 2), Queen::new(2, 4), Line::Column),
+            Conflict::Attacking(Queen::new(2, 2), Queen::new(4, 0), Line::SeDiagonal),
+            Conflict::Blocked(Queen::new(2, 4)),
+        ]);
+
+        // A pawn in between keeps the row clear, but one under a queen doesn't help.
+        let board = Board::with_queens(3, vec![Queen::new(0, 0), Queen::new(2, 0)]).unwrap();
+        assert_eq!(board.conflicts().len(), 1);
+        assert!(board.clone().with_pawns(vec![Square::new(1, 0)]).unwrap().conflicts().is_empty());
+        assert_eq!(
+            board.with_pawns(vec![Square::new(0, 0)]).unwrap().conflicts(),
+            vec![Conflict::Attacking(Queen::new(0, 0), Queen::new(2, 0), Line::Row), Conflict::OnPawn(Queen::new(0, 0))],
+        );
+
+        // On a 4×4 torus these two queens share both diagonals.
+        let board = Board::with_queens(4, vec![Queen::new(0, 0), Queen::new(2, 2)]).unwrap()
+            .with_geometry(Geometry::Toroidal).unwrap();
+        assert_eq!(board.conflicts(), vec![
+            Conflict::Attacking(Queen::new(0, 0), Queen::new(2, 2), Line::SwDiagonal),
+            Conflict::Attacking(Queen::new(0, 0), Queen::new(2, 2), Line::SeDiagonal),
+        ]);
+    }
+
+    #[test]
+    fn test_conflicts_match_is_valid() {
+        for rows in [[0, 1, 2, 3, 4], [1, 3, 0, 2, 4], [4, 2, 0, 3, 1], [2, 0, 3, 1, 4], [0, 2, 4, 1, 3]] {
+            let board = Board::from_rows(&rows).unwrap();
+            assert_eq!(board.conflicts().is_empty(), board.is_valid(), "{:?}", rows);
+            let torus = board.with_geometry(Geometry::Toroidal).unwrap();
+            assert_eq!(torus.conflicts().is_empty(), torus.is_valid(), "{:?}", rows);
+        }
+        for a in 0..16 {
+            for b in a + 1..16 {
+                let board = Board::with_queens(4, vec![Queen::new(a % 4, a / 4), Queen::new(b % 4, b / 4)]).unwrap();
+                assert_eq!(board.conflicts().is_empty(), board.is_valid(), "{} {}", a, b);
+            }
+        }
+    }
+}
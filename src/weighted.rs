@@ -0,0 +1,237 @@
+//! Weighted placements: every square of the board carries a weight, and the search looks for the
+//! solution whose queens' squares add up to the most, or the least.
+//!
+//! It's a branch-and-bound search over the same column-by-column recursion as
+//! [`find_valid_boards`](crate::find_valid_boards). Each column can add at most its heaviest
+//! square, so a partial board is dropped as soon as its weight plus the heaviest squares of the
+//! columns left falls short of the best solution found so far. The first column's rows are
+//! searched in parallel, sharing the best weight between them.
+
+use crate::board::{Board, Square};
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+
+/// A weight for each square of a square board.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Weights {
+    side_size: usize,
+    /// The weights one row after another, starting from the top-left corner.
+    values: Vec<i64>,
+}
+
+/// Whether to look for the heaviest solution or the lightest.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Objective {
+    #[default]
+    Maximize,
+    Minimize,
+}
+
+/// The best solution for some weights, found by [`Board::best_weighted_solution`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct WeightedSolution {
+    /// The total weight of the squares the queens stand on.
+    pub weight: i64,
+    pub board: Board,
+}
+
+impl Weights {
+    /// Weights for a `side_size`×`side_size` board, given one row after another from the
+    /// top-left corner, or `None` if there aren't `side_size * side_size` of them or one is
+    /// further from 0 than `i64::MAX / side_size`. That keeps the total of a queen in every
+    /// column, and its negation, from overflowing.
+    ///
+    /// ```
+    /// use nqueens::Weights;
+    ///
+    /// assert!(Weights::new(2, vec![i64::MAX / 2, 0, 0, -i64::MAX / 2]).is_some());
+    /// assert!(Weights::new(2, vec![i64::MAX / 2 + 1, 0, 0, 0]).is_none());
+    /// assert!(Weights::new(1, vec![i64::MIN]).is_none());
+    /// ```
+    pub fn new(side_size: usize, values: Vec<i64>) -> Option<Weights> {
+        let limit = i64::MAX as u64 / side_size.max(1) as u64;
+        if values.len() == side_size * side_size && values.iter().all(|v| v.unsigned_abs() <= limit) {
+            Some(Weights { side_size, values })
+        } else {
+            None
+        }
+    }
+
+    /// Weights given as a list of rows, or `None` if there aren't as many squares in every row
+    /// as there are rows.
+    pub fn from_rows(rows: &[Vec<i64>]) -> Option<Weights> {
+        if rows.iter().any(|row| row.len() != rows.len()) {
+            return None;
+        }
+        Weights::new(rows.len(), rows.concat())
+    }
+
+    pub fn side_size(&self) -> usize {
+        self.side_size
+    }
+
+    /// The weight of `square`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `square` lies off the board.
+    pub fn get(&self, square: Square) -> i64 {
+        assert!(square.x < self.side_size && square.y < self.side_size);
+        self.values[square.y * self.side_size + square.x]
+    }
+
+    /// The total weight of the squares the queens on `board` stand on.
+    pub fn total(&self, board: &Board) -> i64 {
+        board.queens().iter().map(|&q| self.get(q)).sum()
+    }
+}
+
+impl Board {
+    /// The solution of the n-queens problem on the weights' board whose queens stand on the
+    /// heaviest or the lightest squares, depending on `objective`, or `None` if the board has no
+    /// solutions. Of several equally good solutions, the first in row order is returned.
+    ///
+    /// ```
+    /// use nqueens::{Board, Objective, Weights};
+    ///
+    /// // Every square weighs its column times its row, so the best boards keep the queens near the
+    /// // main diagonal.
+    /// let weights = Weights::new(6, (0..36).map(|i| (i % 6) * (i / 6)).collect()).unwrap();
+    /// let best = Board::best_weighted_solution(&weights, Objective::Maximize).unwrap();
+    /// assert_eq!(best.weight, 41);
+    /// assert!(best.board.is_complete() && best.board.is_valid());
+    /// ```
+    pub fn best_weighted_solution(weights: &Weights, objective: Objective) -> Option<WeightedSolution> {
+        let n = weights.side_size();
+        if n == 0 {
+            return Some(WeightedSolution { weight: 0, board: Board::new(0) });
+        }
+        let sign = match objective {
+            Objective::Maximize => 1,
+            Objective::Minimize => -1,
+        };
+        let weight = |square: Square| sign * weights.get(square);
+
+        // The most the columns from each one onwards can add, one square per column.
+        let mut bounds = vec![0; n + 1];
+        for col in (0..n).rev() {
+            let heaviest = (0..n).map(|row| weight(Square::new(col, row))).max().unwrap_or(0);
+            bounds[col] = bounds[col + 1] + heaviest;
+        }
+
+        let search = WeightedSearch {
+            weight: &weight,
+            bounds,
+            best_weight: AtomicI64::new(i64::MIN),
+            best: Mutex::new(None),
+        };
+        Board::new(n).parallel_valid_direct_children_with_queen_in_col(0)
+            .for_each(|board| {
+                let so_far = weight(board.queens()[0]);
+                search.search(&board, 1, so_far);
+            });
+        search.best.into_inner().unwrap().map(|(weight, board)| WeightedSolution { weight: sign * weight, board })
+    }
+}
+
+struct WeightedSearch<'a> {
+    /// The weight of each square, negated when minimizing.
+    weight: &'a (dyn Fn(Square) -> i64 + Sync),
+    /// For each column, the most the columns from it onwards can add.
+    bounds: Vec<i64>,
+    /// The weight of `best`, readable without taking the lock.
+    best_weight: AtomicI64,
+    best: Mutex<Option<(i64, Board)>>,
+}
+
+impl WeightedSearch<'_> {
+    fn search(&self, board: &Board, col: usize, so_far: i64) {
+        // Ties go on searching, so that the first of the best solutions in row order wins.
+        if so_far + self.bounds[col] < self.best_weight.load(Ordering::Relaxed) {
+            return;
+        }
+        if board.is_complete() {
+            self.offer(board, so_far);
+            return;
+        }
+        for child in board.valid_direct_children_with_queen_in_col(col) {
+            let queen = child.queens().iter().find(|q| q.col() == col).copied().expect("child has a queen in the column");
+            self.search(&child, col + 1, so_far + (self.weight)(queen));
+        }
+    }
+
+    /// Keeps `board` if it's better than the best solution so far, or as good and earlier in row
+    /// order.
+    fn offer(&self, board: &Board, weight: i64) {
+        let mut best = self.best.lock().unwrap();
+        let better = match &*best {
+            None => true,
+            Some((best_weight, best_board)) => {
+                weight > *best_weight || (weight == *best_weight && board.queens() < best_board.queens())
+            }
+        };
+        if better {
+            *best = Some((weight, board.clone()));
+            self.best_weight.store(weight, Ordering::Relaxed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fixed scramble of weights, some of them negative.
+    fn scrambled(side_size: usize) -> Weights {
+        let values = (0..side_size * side_size).map(|i| (i as i64 * 7919 % 101) - 30).collect();
+        Weights::new(side_size, values).unwrap()
+    }
+
+    #[test]
+    fn test_best_weighted_solution() {
+        for side_size in 0..=9 {
+            let weights = scrambled(side_size);
+            let solutions: Vec<_> = Board::solutions(side_size).collect();
+            for &objective in &[Objective::Maximize, Objective::Minimize] {
+                let best = solutions.iter()
+                    .map(|board| (weights.total(board), board))
+                    .reduce(|a, b| {
+                        let better = match objective {
+                            Objective::Maximize => b.0 > a.0,
+                            Objective::Minimize => b.0 < a.0,
+                        };
+                        if better { b } else { a }
+                    })
+                    .map(|(weight, board)| WeightedSolution { weight, board: board.clone() });
+                assert_eq!(Board::best_weighted_solution(&weights, objective), best, "side size {}", side_size);
+            }
+        }
+    }
+
+    #[test]
+    fn test_extreme_weights() {
+        // Weights as large as they're allowed to be don't overflow, whichever way the search
+        // goes.
+        let limit = i64::MAX / 6;
+        let weights = Weights::new(6, (0..36).map(|i| if i % 5 == 0 { limit } else { -limit }).collect()).unwrap();
+        let solutions: Vec<_> = Board::solutions(6).collect();
+        let heaviest = solutions.iter().map(|board| weights.total(board)).max();
+        let lightest = solutions.iter().map(|board| weights.total(board)).min();
+        assert_eq!(Board::best_weighted_solution(&weights, Objective::Maximize).map(|s| s.weight), heaviest);
+        assert_eq!(Board::best_weighted_solution(&weights, Objective::Minimize).map(|s| s.weight), lightest);
+    }
+
+    #[test]
+    fn test_weights() {
+        let weights = Weights::from_rows(&[vec![1, 2], vec![3, 4]]).unwrap();
+        assert_eq!(weights.get(Square::new(1, 0)), 2);
+        assert_eq!(weights.get(Square::new(0, 1)), 3);
+        assert!(Weights::from_rows(&[vec![1, 2], vec![3]]).is_none());
+        assert!(Weights::new(3, vec![0; 8]).is_none());
+        assert!(Weights::new(3, vec![i64::MAX / 3 + 1; 9]).is_none());
+
+        let board = Board::from_rows(&[1, 0]).unwrap();
+        assert_eq!(weights.total(&board), 5);
+    }
+}